@@ -0,0 +1,591 @@
+use std::{
+	fs,
+	path::{
+		Path,
+		PathBuf,
+	},
+	sync::OnceLock,
+};
+
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use clap::{
+	ArgMatches,
+	ValueEnum,
+};
+use git2::{
+	Oid,
+	Repository,
+};
+use regex::Regex;
+use resin::ConventionalCommit;
+use semver::Version;
+
+use crate::{
+	conf::Config,
+	git::{
+		commit_paths,
+		gen_message,
+		verify_message,
+		verify_staged,
+	},
+	hook::run_git_hook,
+	inputs::Inputs,
+	utils::{
+		output_failure,
+		output_info,
+		output_success,
+	},
+};
+
+static PACKAGE_JSON_VERSION: OnceLock<Regex> = OnceLock::new();
+static PACKAGE_LOCK_ROOT_VERSION: OnceLock<Regex> = OnceLock::new();
+
+/// Manifests whose version we know how to rewrite
+static MANIFESTS: [&str; 2] = ["Cargo.toml", "package.json"];
+
+/// Lockfiles that record the version of the manifest's package as well
+static LOCKFILES: [(&str, &str); 2] = [
+	("Cargo.toml", "Cargo.lock"),
+	("package.json", "package-lock.json"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+	Patch,
+	Minor,
+	Major,
+}
+
+/// How breaking changes are handled while the major version is 0
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PreMajor {
+	/// Breaking changes bump the minor version, features the patch version
+	Minor,
+	/// Breaking changes release 1.0.0
+	Major,
+}
+
+/// Entry point of `resin bump`
+pub fn run(config: &Config, args: &ArgMatches) -> Result<bool> {
+	let repo = Repository::open_from_env()?;
+	let policy = *args.get_one::<PreMajor>("pre-major").unwrap();
+	let (current, tag) = match latest_version(&repo)? {
+		Some((version, oid)) => (version, Some(oid)),
+		// projects that were never tagged still have a version in their manifest
+		None => (
+			repo
+				.workdir()
+				.and_then(manifest_version)
+				.unwrap_or(Version::new(0, 0, 0)),
+			None,
+		),
+	};
+	let messages = messages_since(&repo, tag)?;
+	let Some(level) = level(config, messages.iter().map(String::as_str)) else {
+		output_info(&format!(
+			"No features, fixes or breaking changes since {current}, nothing to release"
+		));
+		return Ok(true);
+	};
+	let next = next_version(&current, level, policy);
+	output_success(&format!("{current} -> {next}"));
+
+	let commit = args.get_flag("commit");
+	if !(args.get_flag("write") || commit) {
+		return Ok(true);
+	}
+	let workdir = repo
+		.workdir()
+		.context("Can't rewrite manifests in a bare repository")?;
+	check_downgrade(workdir, &next)?;
+	let mut paths = Vec::new();
+	for manifest in MANIFESTS {
+		let path = workdir.join(manifest);
+		if !path.exists() {
+			continue;
+		}
+		write_version(&path, &next)?;
+		output_success(&format!("Updated version in {manifest}"));
+		paths.push(PathBuf::from(manifest));
+		// otherwise the next build dirties the tree right after the release
+		if let Some(lockfile) = write_lockfile(workdir, manifest, &next)? {
+			output_success(&format!("Updated version in {lockfile}"));
+			paths.push(PathBuf::from(lockfile));
+		}
+	}
+	if commit {
+		if paths.is_empty() {
+			bail!("There is no manifest to commit");
+		}
+		let description = format!("v{next}");
+		let inputs = Inputs {
+			change_type: "chore",
+			scope: Some("release".into()),
+			description,
+			long_description: String::new(),
+			breaking_changes: String::new(),
+			tickets: Vec::new(),
+		};
+		let mut message = gen_message(&repo, config, &inputs);
+		if !args.get_flag("no-verify") {
+			verify_staged(&repo)?;
+			message = verify_message(&repo, &message)?;
+		}
+		commit_paths(&repo, &paths, &message)?;
+		output_success("Committed release");
+		// like with git, a failing post-commit hook doesn't undo the commit
+		if let Err(e) = run_git_hook(&repo, "post-commit", &[]) {
+			output_failure(&format!("{e:#}"));
+		}
+	}
+	if args.get_flag("tag") {
+		let head = repo.head()?.peel_to_commit()?;
+		let name = format!("v{next}");
+		repo.tag(&name, head.as_object(), &repo.signature()?, &name, false)?;
+		output_success(&format!("Created tag {name}"));
+	}
+	Ok(true)
+}
+
+fn parse_tag(name: &str) -> Option<Version> {
+	Version::parse(name.strip_prefix('v').unwrap_or(name)).ok()
+}
+
+/// Highest semver tag that is reachable from HEAD
+fn latest_version(repo: &Repository) -> Result<Option<(Version, Oid)>> {
+	let head = repo.head()?.peel_to_commit()?.id();
+	let mut latest: Option<(Version, Oid)> = None;
+	for name in repo.tag_names(None)?.iter().flatten() {
+		let Some(version) = parse_tag(name) else {
+			continue;
+		};
+		let oid = repo
+			.revparse_single(&format!("refs/tags/{name}"))?
+			.peel_to_commit()?
+			.id();
+		let reachable = oid == head || repo.graph_descendant_of(head, oid)?;
+		if reachable && latest.as_ref().is_none_or(|(latest, _)| version > *latest) {
+			latest = Some((version, oid));
+		}
+	}
+	Ok(latest)
+}
+
+fn messages_since(repo: &Repository, tag: Option<Oid>) -> Result<Vec<String>> {
+	let mut revwalk = repo.revwalk()?;
+	revwalk.push_head()?;
+	if let Some(tag) = tag {
+		revwalk.hide(tag)?;
+	}
+	let mut messages = Vec::new();
+	for oid in revwalk {
+		let commit = repo.find_commit(oid?)?;
+		messages.push(commit.message().unwrap_or_default().into());
+	}
+	Ok(messages)
+}
+
+/// The biggest change in the messages decides the bump
+fn level<'a>(config: &Config, messages: impl Iterator<Item = &'a str>) -> Option<Level> {
+	messages
+		.filter_map(|message| ConventionalCommit::parse(message).ok())
+		.filter_map(|commit| {
+			if commit.is_breaking() {
+				return Some(Level::Major);
+			}
+			// `✨: ...` is a feature as well
+			let type_ = config.resolve_type(&commit.type_).unwrap_or(&commit.type_);
+			match type_.to_ascii_lowercase().as_str() {
+				"feat" => Some(Level::Minor),
+				"fix" | "perf" => Some(Level::Patch),
+				_ => None,
+			}
+		})
+		.max()
+}
+
+fn next_version(current: &Version, level: Level, policy: PreMajor) -> Version {
+	// https://semver.org/#spec-item-4
+	let level = match (current.major, policy, level) {
+		(0, PreMajor::Minor, Level::Major) => Level::Minor,
+		(0, PreMajor::Minor, Level::Minor) => Level::Patch,
+		_ => level,
+	};
+	match level {
+		Level::Major => Version::new(current.major + 1, 0, 0),
+		Level::Minor => Version::new(current.major, current.minor + 1, 0),
+		Level::Patch => Version::new(current.major, current.minor, current.patch + 1),
+	}
+}
+
+/// Version of the first manifest in `dir` that has a valid one
+fn manifest_version(dir: &Path) -> Option<Version> {
+	MANIFESTS
+		.iter()
+		.find_map(|manifest| read_version(&dir.join(manifest)))
+}
+
+/// A tag can be behind the manifest, checked before anything is written
+fn check_downgrade(dir: &Path, next: &Version) -> Result<()> {
+	for manifest in MANIFESTS {
+		if let Some(version) = read_version(&dir.join(manifest)) {
+			if version > *next {
+				bail!("{manifest} is already at {version}, refusing to downgrade it to {next}");
+			}
+		}
+	}
+	Ok(())
+}
+
+fn read_version(path: &Path) -> Option<Version> {
+	let content = fs::read_to_string(path).ok()?;
+	let version = match path.file_name()?.to_str()? {
+		"Cargo.toml" => {
+			let manifest = content.parse::<toml::Table>().ok()?;
+			manifest
+				.get("package")?
+				.get("version")?
+				.as_str()?
+				.to_owned()
+		}
+		"package.json" => package_json_regex().captures(&content)?[2].to_owned(),
+		_ => return None,
+	};
+	Version::parse(&version).ok()
+}
+
+fn write_version(path: &Path, version: &Version) -> Result<()> {
+	let content =
+		fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+	let updated = match path.file_name().and_then(|name| name.to_str()) {
+		Some("Cargo.toml") => replace_cargo_version(&content, version),
+		Some("package.json") => replace_package_json_version(&content, version),
+		_ => None,
+	};
+	let Some(updated) = updated else {
+		bail!("Failed to find the version in {}", path.display());
+	};
+	fs::write(path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+	Ok(())
+}
+
+/// Rewrites the lockfile that belongs to `manifest`, if there is one
+fn write_lockfile(dir: &Path, manifest: &str, version: &Version) -> Result<Option<&'static str>> {
+	let Some((_, lockfile)) = LOCKFILES.iter().find(|(owner, _)| *owner == manifest) else {
+		return Ok(None);
+	};
+	let path = dir.join(lockfile);
+	if !path.exists() {
+		return Ok(None);
+	}
+	let content =
+		fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+	let updated = match *lockfile {
+		"Cargo.lock" => cargo_package_name(&fs::read_to_string(dir.join(manifest))?)
+			.and_then(|name| replace_cargo_lock_version(&content, &name, version)),
+		_ => replace_package_lock_version(&content, version),
+	};
+	let Some(updated) = updated else {
+		bail!("Failed to find the version in {}", path.display());
+	};
+	fs::write(&path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+	Ok(Some(lockfile))
+}
+
+fn cargo_package_name(content: &str) -> Option<String> {
+	let manifest = content.parse::<toml::Table>().ok()?;
+	Some(manifest.get("package")?.get("name")?.as_str()?.into())
+}
+
+/// Only touches the `[[package]]` entry of `name` without a `source`,
+/// a dependency from crates.io can have the same name
+fn replace_cargo_lock_version(content: &str, name: &str, version: &Version) -> Option<String> {
+	let name_line = format!("name = \"{name}\"");
+	let mut replaced = false;
+	let mut blocks: Vec<Vec<String>> = Vec::new();
+	for line in content.split_inclusive('\n') {
+		match blocks.last_mut() {
+			Some(block) if !line.starts_with("[[package]]") => block.push(line.into()),
+			_ => blocks.push(vec![line.into()]),
+		}
+	}
+	for block in &mut blocks {
+		let is_root = block.iter().any(|line| line.trim_end() == name_line)
+			&& !block.iter().any(|line| line.starts_with("source = "));
+		if !is_root || replaced {
+			continue;
+		}
+		for line in block.iter_mut() {
+			if line.starts_with("version = ") {
+				let ending = if line.ends_with('\n') { "\n" } else { "" };
+				*line = format!("version = \"{version}\"{ending}");
+				replaced = true;
+			}
+		}
+	}
+	replaced.then(|| blocks.concat().concat())
+}
+
+/// The top level `version` and, since lockfile version 2, the one of `packages[""]`
+fn replace_package_lock_version(content: &str, version: &Version) -> Option<String> {
+	let content = replace_package_json_version(content, version)?;
+	let regex = PACKAGE_LOCK_ROOT_VERSION
+		.get_or_init(|| Regex::new(r#"("":\s*\{[^{}]*?"version"\s*:\s*)"[^"]*""#).unwrap());
+	Some(
+		regex
+			.replace(&content, format!("${{1}}\"{version}\""))
+			.into(),
+	)
+}
+
+/// Only touches the `version` of the `[package]` table, so the formatting is kept
+fn replace_cargo_version(content: &str, version: &Version) -> Option<String> {
+	let mut in_package = false;
+	let mut replaced = false;
+	let mut lines = Vec::new();
+	for line in content.split_inclusive('\n') {
+		let trimmed = line.trim_start();
+		if trimmed.starts_with('[') {
+			in_package = trimmed.starts_with("[package]");
+		} else if in_package && !replaced {
+			if let Some(rest) = trimmed.strip_prefix("version") {
+				if rest.trim_start().starts_with('=') {
+					let ending = if line.ends_with('\n') { "\n" } else { "" };
+					lines.push(format!("version = \"{version}\"{ending}"));
+					replaced = true;
+					continue;
+				}
+			}
+		}
+		lines.push(line.into());
+	}
+	replaced.then(|| lines.concat())
+}
+
+fn package_json_regex() -> &'static Regex {
+	PACKAGE_JSON_VERSION.get_or_init(|| Regex::new(r#"("version"\s*:\s*)"([^"]*)""#).unwrap())
+}
+
+fn replace_package_json_version(content: &str, version: &Version) -> Option<String> {
+	let regex = package_json_regex();
+	regex.is_match(content).then(|| {
+		regex
+			.replace(content, format!("${{1}}\"{version}\""))
+			.into()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+	use crate::test_utils::commit;
+
+	fn version(version: &str) -> Version {
+		Version::parse(version).unwrap()
+	}
+
+	#[test]
+	fn test_level() {
+		assert_eq!(
+			level(
+				&Config::default(),
+				["chore: x", "docs: y", "nope"].into_iter()
+			),
+			None
+		);
+		assert_eq!(
+			level(&Config::default(), ["chore: x", "fix: y"].into_iter()),
+			Some(Level::Patch)
+		);
+		assert_eq!(
+			level(&Config::default(), ["perf: x"].into_iter()),
+			Some(Level::Patch)
+		);
+		assert_eq!(
+			level(&Config::default(), ["fix: x", "feat: y"].into_iter()),
+			Some(Level::Minor)
+		);
+		assert_eq!(
+			level(&Config::default(), ["feat: x", "chore!: y"].into_iter()),
+			Some(Level::Major)
+		);
+		assert_eq!(
+			level(
+				&Config::default(),
+				["fix: x\n\nBREAKING CHANGE: gone"].into_iter()
+			),
+			Some(Level::Major)
+		);
+	}
+
+	#[test]
+	fn test_next_version() {
+		let current = version("1.7.0");
+		assert_eq!(
+			next_version(&current, Level::Patch, PreMajor::Minor),
+			version("1.7.1")
+		);
+		assert_eq!(
+			next_version(&current, Level::Minor, PreMajor::Minor),
+			version("1.8.0")
+		);
+		assert_eq!(
+			next_version(&current, Level::Major, PreMajor::Minor),
+			version("2.0.0")
+		);
+	}
+
+	#[test]
+	fn test_pre_major() {
+		let current = version("0.3.1");
+		assert_eq!(
+			next_version(&current, Level::Major, PreMajor::Minor),
+			version("0.4.0")
+		);
+		assert_eq!(
+			next_version(&current, Level::Minor, PreMajor::Minor),
+			version("0.3.2")
+		);
+		assert_eq!(
+			next_version(&current, Level::Major, PreMajor::Major),
+			version("1.0.0")
+		);
+		assert_eq!(
+			next_version(&current, Level::Minor, PreMajor::Major),
+			version("0.4.0")
+		);
+	}
+
+	#[test]
+	fn test_tag_requires_commit() {
+		let parse = |args: &[&str]| crate::cli::setup().try_get_matches_from(args);
+		assert!(parse(&["resin", "bump", "--tag"]).is_err());
+		assert!(parse(&["resin", "bump", "--write", "--tag"]).is_err());
+		assert!(parse(&["resin", "bump", "--commit", "--tag"]).is_ok());
+	}
+
+	#[test]
+	fn test_cargo_version() {
+		let content =
+			"[package]\nname = \"resin\"\nversion = \"1.7.0\"\n\n[dependencies]\nversion = \"1\"\n";
+		assert_eq!(
+			replace_cargo_version(content, &version("1.8.0")).unwrap(),
+			"[package]\nname = \"resin\"\nversion = \"1.8.0\"\n\n[dependencies]\nversion = \"1\"\n"
+		);
+		assert_eq!(
+			replace_cargo_version("[workspace]\n", &version("1.8.0")),
+			None
+		);
+	}
+
+	#[test]
+	fn test_package_json_version() {
+		let content = "{\n  \"name\": \"x\",\n  \"version\": \"0.1.0\"\n}\n";
+		assert_eq!(
+			replace_package_json_version(content, &version("0.2.0")).unwrap(),
+			"{\n  \"name\": \"x\",\n  \"version\": \"0.2.0\"\n}\n"
+		);
+	}
+
+	#[test]
+	fn test_cargo_lock_version() {
+		let content = "version = 4\n\n[[package]]\nname = \"resin\"\nversion = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"resin\"\nversion = \"1.7.0\"\ndependencies = [\n \"anyhow\",\n]\n";
+		assert_eq!(
+			replace_cargo_lock_version(content, "resin", &version("1.8.0")).unwrap(),
+			content.replace("version = \"1.7.0\"", "version = \"1.8.0\"")
+		);
+		assert_eq!(
+			replace_cargo_lock_version(content, "other", &version("1.8.0")),
+			None
+		);
+	}
+
+	#[test]
+	fn test_package_lock_version() {
+		let content = "{\n  \"name\": \"x\",\n  \"version\": \"0.1.0\",\n  \"lockfileVersion\": 3,\n  \
+		               \"packages\": {\n    \"\": {\n      \"name\": \"x\",\n      \"version\": \
+		               \"0.1.0\",\n      \"dependencies\": {\n        \"y\": \"^1.0.0\"\n      }\n    \
+		               },\n    \"node_modules/y\": {\n      \"version\": \"1.0.0\"\n    }\n  }\n}\n";
+		assert_eq!(
+			replace_package_lock_version(content, &version("0.2.0")).unwrap(),
+			content.replace("\"version\": \"0.1.0\"", "\"version\": \"0.2.0\"")
+		);
+	}
+
+	#[test]
+	fn test_write_lockfile() {
+		let dir = TempDir::new().unwrap();
+		fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+		assert_eq!(
+			write_lockfile(dir.path(), "Cargo.toml", &version("1.0.0")).unwrap(),
+			None
+		);
+		fs::write(
+			dir.path().join("Cargo.lock"),
+			"[[package]]\nname = \"x\"\nversion = \"0.1.0\"\n",
+		)
+		.unwrap();
+		assert_eq!(
+			write_lockfile(dir.path(), "Cargo.toml", &version("1.0.0")).unwrap(),
+			Some("Cargo.lock")
+		);
+		assert_eq!(
+			fs::read_to_string(dir.path().join("Cargo.lock")).unwrap(),
+			"[[package]]\nname = \"x\"\nversion = \"1.0.0\"\n"
+		);
+	}
+
+	#[test]
+	fn test_manifest_version() {
+		let dir = TempDir::new().unwrap();
+		assert_eq!(manifest_version(dir.path()), None);
+		fs::write(
+			dir.path().join("package.json"),
+			"{\n  \"name\": \"x\",\n  \"version\": \"0.3.1\"\n}\n",
+		)
+		.unwrap();
+		assert_eq!(manifest_version(dir.path()), Some(version("0.3.1")));
+		fs::write(
+			dir.path().join("Cargo.toml"),
+			"[package]\nname = \"x\"\nversion = \"1.2.0\"\n\n[dependencies]\nsemver = { version = \"1\" \
+			 }\n",
+		)
+		.unwrap();
+		assert_eq!(manifest_version(dir.path()), Some(version("1.2.0")));
+		// workspaces without a package
+		fs::write(dir.path().join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+		assert_eq!(manifest_version(dir.path()), Some(version("0.3.1")));
+
+		assert!(check_downgrade(dir.path(), &version("0.3.1")).is_ok());
+		assert!(check_downgrade(dir.path(), &version("0.4.0")).is_ok());
+		assert!(check_downgrade(dir.path(), &version("0.3.0")).is_err());
+	}
+
+	#[test]
+	fn test_latest_version() {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+		let first = repo.find_commit(commit(&repo, "feat: first")).unwrap();
+		repo
+			.tag_lightweight("v0.1.0", first.as_object(), false)
+			.unwrap();
+		repo
+			.tag_lightweight("not-a-version", first.as_object(), false)
+			.unwrap();
+		commit(&repo, "fix: second");
+
+		let (latest, oid) = latest_version(&repo).unwrap().unwrap();
+		assert_eq!(latest, version("0.1.0"));
+		assert_eq!(
+			messages_since(&repo, Some(oid)).unwrap(),
+			vec![String::from("fix: second")]
+		);
+	}
+}
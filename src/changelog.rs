@@ -0,0 +1,358 @@
+use std::{
+	fmt::Write,
+	fs,
+	path::PathBuf,
+};
+
+use anyhow::{
+	Context,
+	Result,
+};
+use clap::ArgMatches;
+use git2::{
+	Commit,
+	DescribeOptions,
+	Oid,
+	Repository,
+	Sort,
+};
+use resin::{
+	ConventionalCommit,
+	Separator,
+};
+
+use crate::{
+	conf::{
+		Config,
+		TicketConfig,
+	},
+	utils::output_success,
+};
+
+static FILENAME: &str = "CHANGELOG.md";
+
+/// Footers that reference tickets or issues, next to the configured `ticket.key`
+static TICKET_TOKENS: [&str; 5] = ["Refs", "Closes", "Fixes", "Resolves", "Ticket"];
+
+#[derive(Debug, PartialEq)]
+struct Entry {
+	commit: ConventionalCommit,
+	id: String,
+	tickets: Vec<String>,
+}
+
+impl Entry {
+	fn new(commit: ConventionalCommit, oid: Oid, ticket_config: &TicketConfig) -> Self {
+		let mut tickets: Vec<String> = commit
+			.footers
+			.iter()
+			.filter(|footer| {
+				TICKET_TOKENS
+					.iter()
+					.chain([&ticket_config.get_key()])
+					.any(|token| footer.token.eq_ignore_ascii_case(token))
+			})
+			.map(|footer| match footer.separator {
+				Separator::Colon => footer.value.trim().to_string(),
+				Separator::Hash => format!("#{}", footer.value.trim()),
+			})
+			.collect();
+		for ticket in ticket_config.parse_message(&commit.description, commit.body.as_deref()) {
+			if !tickets.contains(&ticket) {
+				tickets.push(ticket);
+			}
+		}
+		Self {
+			commit,
+			id: oid.to_string()[..7].into(),
+			tickets,
+		}
+	}
+
+	fn render(&self, out: &mut String) {
+		out.push_str("- ");
+		if let Some(scope) = &self.commit.scope {
+			let _ = write!(out, "**{scope}:** ");
+		}
+		let _ = write!(out, "{} ({})", self.commit.description, self.id);
+		if !self.tickets.is_empty() {
+			let _ = write!(out, " [{}]", self.tickets.join(", "));
+		}
+		out.push('\n');
+	}
+}
+
+/// Entry point of `resin changelog`
+pub fn run(config: &Config, args: &ArgMatches) -> Result<bool> {
+	let repo = Repository::open_from_env()?;
+	let to = args.get_one::<String>("to").unwrap();
+	let from = match args.get_one::<String>("from") {
+		Some(from) => Some(from.clone()),
+		None => latest_tag(&repo, to),
+	};
+	let to_commit = repo
+		.revparse_single(to)
+		.and_then(|object| object.peel_to_commit())
+		.with_context(|| format!("Failed to find {to}"))?;
+	let title = match args.get_one::<String>("title") {
+		Some(title) => title.clone(),
+		None if repo.find_reference(&format!("refs/tags/{to}")).is_ok() => to.clone(),
+		None => "Unreleased".into(),
+	};
+
+	let entries = collect(config, &repo, from.as_deref(), to_commit.id())?;
+	let changelog = render(config, &title, &format_date(&to_commit), &entries);
+	if args.get_flag("stdout") {
+		print!("{changelog}");
+		return Ok(true);
+	}
+
+	let path = match args.get_one::<PathBuf>("output") {
+		Some(path) => path.clone(),
+		None => repo.workdir().unwrap_or(repo.path()).join(FILENAME),
+	};
+	let existing = fs::read_to_string(&path).unwrap_or_default();
+	fs::write(&path, prepend(&existing, &changelog))
+		.with_context(|| format!("Failed to write {}", path.display()))?;
+	output_success(&format!(
+		"Added {} commits to {}",
+		entries.len(),
+		path.display()
+	));
+	Ok(true)
+}
+
+/// The most recent tag reachable from `to`
+fn latest_tag(repo: &Repository, to: &str) -> Option<String> {
+	let object = repo.revparse_single(to).ok()?;
+	let mut options = DescribeOptions::new();
+	options.describe_tags();
+	let describe = object.describe(&options).ok()?;
+	let tag = describe
+		.format(Some(git2::DescribeFormatOptions::new().abbreviated_size(0)))
+		.ok()?;
+	// `to` is the tag itself, so we want the changes since the one before
+	if object.peel_to_commit().ok()?.id()
+		== repo.revparse_single(&tag).ok()?.peel_to_commit().ok()?.id()
+	{
+		return latest_tag(repo, &format!("{tag}^"));
+	}
+	Some(tag)
+}
+
+/// Every conventional commit between `from` (exclusive) and `to`, newest first
+fn collect(config: &Config, repo: &Repository, from: Option<&str>, to: Oid) -> Result<Vec<Entry>> {
+	let mut revwalk = repo.revwalk()?;
+	revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+	revwalk.push(to)?;
+	if let Some(from) = from {
+		let from = repo
+			.revparse_single(from)
+			.and_then(|object| object.peel_to_commit())
+			.with_context(|| format!("Failed to find {from}"))?;
+		revwalk.hide(from.id())?;
+	}
+	let mut entries = Vec::new();
+	for oid in revwalk {
+		let commit = repo.find_commit(oid?)?;
+		// merge commits don't describe changes themselves
+		if commit.parent_count() > 1 {
+			continue;
+		}
+		if let Ok(mut parsed) = ConventionalCommit::parse(commit.message().unwrap_or_default()) {
+			// group gitmoji headers with the type they stand for
+			if let Some(type_) = config.resolve_type(&parsed.type_) {
+				parsed.type_ = type_.into();
+			}
+			entries.push(Entry::new(parsed, commit.id(), config.get_ticket()));
+		}
+	}
+	Ok(entries)
+}
+
+/// Groups the entries by type in the order they are configured
+fn render(config: &Config, title: &str, date: &str, entries: &[Entry]) -> String {
+	let mut out = format!("## {title} ({date})\n");
+
+	let breaking: Vec<&Entry> = entries
+		.iter()
+		.filter(|entry| entry.commit.is_breaking())
+		.collect();
+	if !breaking.is_empty() {
+		out.push_str("\n### ⚠ BREAKING CHANGES\n\n");
+		for entry in breaking {
+			entry.render(&mut out);
+			for note in entry
+				.commit
+				.footers
+				.iter()
+				.filter(|footer| footer.is_breaking())
+			{
+				// continuation lines are indented in the footer already
+				for line in note.value.lines() {
+					let _ = writeln!(out, "  {}", line.trim_start());
+				}
+			}
+		}
+	}
+
+	let mut types: Vec<&str> = config.get_type_items().iter().map(String::as_str).collect();
+	// types that aren't configured (anymore) still belong in the changelog
+	for entry in entries {
+		if !types.contains(&entry.commit.type_.as_str()) {
+			types.push(&entry.commit.type_);
+		}
+	}
+	for type_ in types {
+		let group: Vec<&Entry> = entries
+			.iter()
+			.filter(|entry| entry.commit.type_ == type_)
+			.collect();
+		if group.is_empty() {
+			continue;
+		}
+		let _ = write!(out, "\n### {}\n\n", config.get_type_name(type_));
+		for entry in group {
+			entry.render(&mut out);
+		}
+	}
+	out
+}
+
+/// New releases go on top, right below the title of the file
+fn prepend(existing: &str, changelog: &str) -> String {
+	if existing.trim().is_empty() {
+		return format!("# Changelog\n\n{changelog}");
+	}
+	match existing.split_once('\n') {
+		Some((title, rest)) if title.starts_with("# ") => {
+			format!("{title}\n\n{changelog}\n{}", rest.trim_start_matches('\n'))
+		}
+		_ => format!("{changelog}\n{existing}"),
+	}
+}
+
+/// `YYYY-MM-DD` of the commit time
+fn format_date(commit: &Commit) -> String {
+	let time = commit.time();
+	let seconds = time.seconds() + i64::from(time.offset_minutes()) * 60;
+	// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+	let days = seconds.div_euclid(86_400) + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days.rem_euclid(146_097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	};
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+	format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+	use crate::test_utils::commit_at;
+
+	fn entry(message: &str) -> Entry {
+		Entry::new(
+			ConventionalCommit::parse(message).unwrap(),
+			Oid::zero(),
+			&TicketConfig::default(),
+		)
+	}
+
+	#[test]
+	fn test_render() {
+		let entries = vec![
+			entry("fix(git): handle unborn HEAD\n\nRefs: PROJ-12"),
+			entry("feat!: drop the old format\n\nBREAKING CHANGE: old configs are ignored"),
+			entry("feat(lint): add lint"),
+			entry("wip: unknown type"),
+		];
+		let changelog = render(&Config::default(), "v2.0.0", "2024-01-02", &entries);
+		assert_eq!(
+			changelog,
+			"## v2.0.0 (2024-01-02)
+
+### ⚠ BREAKING CHANGES
+
+- drop the old format (0000000)
+  old configs are ignored
+
+### Features
+
+- drop the old format (0000000)
+- **lint:** add lint (0000000)
+
+### Bug Fixes
+
+- **git:** handle unborn HEAD (0000000) [PROJ-12]
+
+### wip
+
+- unknown type (0000000)
+"
+		);
+	}
+
+	#[test]
+	fn test_tickets_from_body() {
+		let entry = entry("feat: add lint\n\nPROJ-34\nsome body\n\nCloses #5");
+		assert_eq!(entry.tickets, vec![String::from("#5"), "PROJ-34".into()]);
+	}
+
+	#[test]
+	fn test_no_tickets_from_prose() {
+		let prose = entry("fix: read files as utf-8\n\nThe parser assumed utf-8 and SHA-256 sums.");
+		assert!(prose.tickets.is_empty());
+	}
+
+	#[test]
+	fn test_tickets_with_config() {
+		let config = Config::from_toml("[ticket]\nplacement = 'header'\nkey = 'Jira'");
+		let entry = Entry::new(
+			ConventionalCommit::parse("feat: PROJ-1 add utf-8 support\n\nJira: PROJ-2").unwrap(),
+			Oid::zero(),
+			config.get_ticket(),
+		);
+		assert_eq!(entry.tickets, vec![String::from("PROJ-2"), "PROJ-1".into()]);
+	}
+
+	#[test]
+	fn test_prepend() {
+		assert_eq!(prepend("", "## v1\n"), "# Changelog\n\n## v1\n");
+		assert_eq!(
+			prepend("# Changelog\n\n## v1\n", "## v2\n"),
+			"# Changelog\n\n## v2\n\n## v1\n"
+		);
+		assert_eq!(prepend("## v1\n", "## v2\n"), "## v2\n\n## v1\n");
+	}
+
+	#[test]
+	fn test_collect_since_tag() {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+		let first = commit_at(&repo, "feat: first", 0);
+		repo
+			.tag_lightweight("v1.0.0", &repo.find_object(first, None).unwrap(), false)
+			.unwrap();
+		commit_at(&repo, "not conventional", 1);
+		let last = commit_at(&repo, "fix: second", 86_400 * 365);
+
+		assert_eq!(latest_tag(&repo, "HEAD").as_deref(), Some("v1.0.0"));
+		assert_eq!(latest_tag(&repo, "v1.0.0"), None);
+		let entries = collect(&Config::default(), &repo, Some("v1.0.0"), last).unwrap();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].commit.description, "second");
+		assert_eq!(format_date(&repo.find_commit(last).unwrap()), "1971-01-01");
+	}
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{
 	builder::{
 		styling::AnsiColor,
@@ -7,10 +9,17 @@ use clap::{
 	crate_description,
 	crate_name,
 	crate_version,
+	value_parser,
 	Arg,
+	ArgAction,
 	Command,
 };
 
+use crate::{
+	bump::PreMajor,
+	hook::Hook,
+};
+
 pub fn setup() -> Command {
 	let styles = Styles::styled()
 		.header(AnsiColor::Yellow.on_default())
@@ -32,8 +41,234 @@ pub fn setup() -> Command {
 		)
 		.arg(
 			Arg::new("all")
-				.help("Run git add . before committing the the changes")
+				.help("Stage every change like git add -A before committing, including deletions")
 				.short('a')
-				.long("all"),
+				.long("all")
+				.action(ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("update")
+				.help("Stage changes of tracked files like git add -u before committing")
+				.short('u')
+				.long("update")
+				.action(ArgAction::SetTrue)
+				.conflicts_with("all"),
+		)
+		.arg(
+			Arg::new("patch")
+				.help("Interactively choose hunks to stage before committing")
+				.short('P')
+				.long("patch")
+				.action(ArgAction::SetTrue)
+				.conflicts_with_all(["all", "update"]),
+		)
+		.arg(
+			Arg::new("no-verify")
+				.help("Skip the pre-commit and commit-msg hooks")
+				.short('n')
+				.long("no-verify")
+				.action(ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("push")
+				.help("Push the current branch to its upstream after committing")
+				.short('p')
+				.long("push")
+				.action(ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("type")
+				.help("Type of the change (skips the type prompt)")
+				.short('t')
+				.long("type")
+				.value_name("TYPE"),
+		)
+		.arg(
+			Arg::new("scope")
+				.help("Scope of the change (skips the scope prompt)")
+				.short('s')
+				.long("scope")
+				.value_name("SCOPE"),
 		)
+		.arg(
+			Arg::new("message")
+				.help("Short description of the change (skips the description prompt)")
+				.short('m')
+				.long("message")
+				.value_name("DESCRIPTION"),
+		)
+		.arg(
+			Arg::new("body")
+				.help("Longer description of the change (skips the editor)")
+				.short('b')
+				.long("body")
+				.value_name("BODY"),
+		)
+		.arg(
+			Arg::new("breaking")
+				.help("Description of the breaking change")
+				.long("breaking")
+				.value_name("DESCRIPTION"),
+		)
+		.arg(
+			Arg::new("ticket")
+				.help("Tickets the change belongs to, can be repeated or comma separated")
+				.long("ticket")
+				.value_name("TICKET")
+				.action(ArgAction::Append)
+				.value_delimiter(','),
+		)
+		.arg(
+			Arg::new("yes")
+				.help("Don't prompt for optional fields that weren't passed as flags")
+				.short('y')
+				.long("yes")
+				.action(ArgAction::SetTrue),
+		)
+		.subcommand(
+			Command::new("lint")
+				.about("Check that commit messages follow the conventional commits format")
+				.arg(
+					Arg::new("file")
+						.help("File containing the commit message, `-` for stdin")
+						.value_parser(value_parser!(PathBuf)),
+				)
+				.arg(
+					Arg::new("range")
+						.help("Lint every commit in a revision range, e.g. `main..HEAD`")
+						.short('r')
+						.long("range")
+						.value_name("RANGE")
+						.conflicts_with("file"),
+				),
+		)
+		.subcommand(
+			Command::new("hook")
+				.about("Manage the git hooks that run resin")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("install")
+						.about("Install a git hook (respects core.hooksPath)")
+						.arg(hook_arg())
+						.arg(
+							Arg::new("force")
+								.help("Overwrite an existing hook that wasn't installed by resin")
+								.short('f')
+								.long("force")
+								.action(ArgAction::SetTrue),
+						),
+				)
+				.subcommand(
+					Command::new("uninstall")
+						.about("Remove a git hook installed by resin")
+						.arg(hook_arg()),
+				)
+				.subcommand(
+					Command::new("commit-msg")
+						.about("Validate the commit message file, called by the commit-msg hook")
+						.arg(
+							Arg::new("file")
+								.required(true)
+								.value_parser(value_parser!(PathBuf)),
+						),
+				)
+				.subcommand(
+					Command::new("prepare-commit-msg")
+						.about("Prompt for the commit message, called by the prepare-commit-msg hook")
+						.arg(
+							Arg::new("file")
+								.required(true)
+								.value_parser(value_parser!(PathBuf)),
+						)
+						.arg(Arg::new("source").help("Where the message comes from, passed by git"))
+						.arg(Arg::new("commit").help("Commit SHA-1, passed by git")),
+				),
+		)
+		.subcommand(
+			Command::new("changelog")
+				.about("Render the conventional commits between two refs as Markdown")
+				.arg(
+					Arg::new("from")
+						.help("Start of the range (exclusive), defaults to the latest tag")
+						.long("from")
+						.value_name("REF"),
+				)
+				.arg(
+					Arg::new("to")
+						.help("End of the range")
+						.long("to")
+						.value_name("REF")
+						.default_value("HEAD"),
+				)
+				.arg(
+					Arg::new("title")
+						.help("Heading of the release, defaults to the tag or `Unreleased`")
+						.long("title")
+						.value_name("TITLE"),
+				)
+				.arg(
+					Arg::new("output")
+						.help("File the changelog is prepended to")
+						.short('o')
+						.long("output")
+						.value_name("FILE")
+						.value_parser(value_parser!(PathBuf)),
+				)
+				.arg(
+					Arg::new("stdout")
+						.help("Print the changelog instead of writing it to a file")
+						.long("stdout")
+						.action(ArgAction::SetTrue)
+						.conflicts_with("output"),
+				),
+		)
+		.subcommand(
+			Command::new("bump")
+				.about("Recommend the next version based on the commits since the latest semver tag")
+				.arg(
+					Arg::new("pre-major")
+						.help("How breaking changes are released while the version is 0.x")
+						.long("pre-major")
+						.value_name("POLICY")
+						.default_value("minor")
+						.value_parser(value_parser!(PreMajor)),
+				)
+				.arg(
+					Arg::new("write")
+						.help("Rewrite the version in Cargo.toml and package.json and their lockfiles")
+						.short('w')
+						.long("write")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("commit")
+						.help("Commit the rewritten manifests as `chore(release)` (implies --write)")
+						.short('c')
+						.long("commit")
+						.action(ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("tag")
+						.help("Create an annotated tag on the release commit")
+						.short('t')
+						.long("tag")
+						.action(ArgAction::SetTrue)
+						// tagging HEAD without the bumped version would be a lie
+						.requires("commit"),
+				)
+				.arg(
+					Arg::new("no-verify")
+						.help("Skip the pre-commit and commit-msg hooks of the release commit")
+						.short('n')
+						.long("no-verify")
+						.action(ArgAction::SetTrue),
+				),
+		)
+}
+
+fn hook_arg() -> Arg {
+	Arg::new("hook")
+		.help("Which hook to manage")
+		.default_value("commit-msg")
+		.value_parser(value_parser!(Hook))
 }
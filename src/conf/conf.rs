@@ -1,8 +1,17 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
+use resin::emoji;
 
 use super::{
+	emoji::{
+		EmojiConfig,
+		EmojiMode,
+	},
 	scope::Scope,
+	ticket::TicketConfig,
 	toml::{
+		TOMLItem,
 		TOMLItemConfig,
 		TOML,
 	},
@@ -13,9 +22,16 @@ use super::{
 pub struct ItemConfig {
 	pub items: Vec<String>,
 	/// capitalize the DEFAULT values
-	capitalize: bool,
+	capitalize: Option<bool>,
 	/// If you really don't like a default
 	ignore: Vec<String>,
+	/// Reject values that are not in `items`
+	strict: Option<bool>,
+	names: HashMap<String, String>,
+	/// Glob -> item, used for suggestions
+	paths: HashMap<String, String>,
+	descriptions: HashMap<String, String>,
+	emojis: HashMap<String, String>,
 }
 
 impl ItemConfig {
@@ -35,15 +51,56 @@ impl ItemConfig {
 		}
 	}
 
+	fn matches(item: &str, ignored: &[String]) -> bool {
+		ignored
+			.iter()
+			.any(|ignored| item.eq_ignore_ascii_case(ignored))
+	}
+
+	/// Removes every ignored item
+	/// and returns the ignored entries that didn't match anything
+	fn apply_ignore(&mut self) -> Vec<String> {
+		let unmatched = self
+			.ignore
+			.iter()
+			.filter(|ignored| !Self::matches(ignored, &self.items))
+			.cloned()
+			.collect();
+		let ignore = &self.ignore;
+		self.items.retain(|item| !Self::matches(item, ignore));
+		unmatched
+	}
+
 	fn merge(&mut self, merge: ItemConfig) {
-		self.items.extend(merge.items);
+		// defaults are often listed again just to describe them
+		for item in merge.items {
+			if !self.items.contains(&item) {
+				self.items.push(item);
+			}
+		}
 		self.ignore.extend(merge.ignore);
-		self.capitalize = self.capitalize || merge.capitalize;
+		// `self` is the more specific layer, so its flags win
+		self.capitalize = self.capitalize.or(merge.capitalize);
+		self.strict = self.strict.or(merge.strict);
+		// names that were already set take precedence
+		for (item, name) in merge.names {
+			self.names.entry(item).or_insert(name);
+		}
+		for (glob, item) in merge.paths {
+			self.paths.entry(glob).or_insert(item);
+		}
+		for (item, description) in merge.descriptions {
+			self.descriptions.entry(item).or_insert(description);
+		}
+		for (item, emoji) in merge.emojis {
+			self.emojis.entry(item).or_insert(emoji);
+		}
 	}
 
 	fn type_default() -> Self {
 		Self {
 			items: Type::get_vec(),
+			paths: Type::default_paths(),
 			..Default::default()
 		}
 	}
@@ -59,7 +116,12 @@ impl ItemConfig {
 pub struct Config {
 	scopes: ItemConfig,
 	types: ItemConfig,
-	sign: bool,
+	sign: Option<bool>,
+	breaking_exclamation: Option<bool>,
+	ticket: TicketConfig,
+	emoji: EmojiConfig,
+	/// Problems with the config that don't stop resin from working
+	warnings: Vec<String>,
 }
 
 impl Default for Config {
@@ -67,50 +129,191 @@ impl Default for Config {
 		Self {
 			types: ItemConfig::type_default(),
 			scopes: ItemConfig::scope_default(),
-			sign: false,
+			sign: None,
+			breaking_exclamation: None,
+			ticket: TicketConfig::default(),
+			emoji: EmojiConfig::default(),
+			warnings: Vec::new(),
 		}
 	}
 }
 
 impl Config {
+	/// Only worth showing when somebody is looking, not in hooks or `lint`
+	pub fn get_warnings(&self) -> &[String] {
+		&self.warnings
+	}
+
 	pub fn get_signoff(&self) -> bool {
-		self.sign
+		self.sign.unwrap_or_default()
+	}
+
+	pub fn get_ticket(&self) -> &TicketConfig {
+		&self.ticket
+	}
+
+	/// Defaults to `true`
+	pub fn get_breaking_exclamation(&self) -> bool {
+		self.breaking_exclamation.unwrap_or(true)
 	}
 
 	pub fn get_scope_items(&self) -> &[String] {
 		self.scopes.items.as_slice()
 	}
 
+	/// Configured description, else the one from the spec
+	pub fn get_type_description(&self, type_: &str) -> Option<&str> {
+		self
+			.types
+			.descriptions
+			.get(type_)
+			.map(String::as_str)
+			.or_else(|| Type::default_description(type_))
+	}
+
+	pub fn get_emoji(&self) -> &EmojiConfig {
+		&self.emoji
+	}
+
+	/// Configured emoji, the gitmoji defaults only with the emoji mode on
+	pub fn get_type_emoji(&self, type_: &str) -> Option<&str> {
+		self
+			.types
+			.emojis
+			.get(type_)
+			.map(String::as_str)
+			.or_else(|| {
+				self
+					.emoji
+					.get_mode()
+					.and_then(|_| Type::default_emoji(type_))
+			})
+	}
+
+	/// The configured type `type_` stands for, which can also be its emoji
+	pub fn resolve_type<'a>(&'a self, type_: &str) -> Option<&'a str> {
+		let items = self.get_type_items();
+		items
+			.iter()
+			.find(|item| *item == type_)
+			.or_else(|| {
+				emoji::is_emoji(type_).then_some(())?;
+				items.iter().find(|item| {
+					self
+						.get_type_emoji(item)
+						.is_some_and(|item_emoji| emoji::matches(item_emoji, type_))
+				})
+			})
+			.map(String::as_str)
+	}
+
+	/// Columns the type takes up in the header, including its emoji
+	pub fn type_header_len(&self, type_: &str) -> usize {
+		let emoji = self
+			.get_type_emoji(type_)
+			.map(|emoji| self.emoji.format(emoji));
+		match (self.emoji.get_mode(), emoji) {
+			(Some(EmojiMode::Prefix), Some(emoji)) => emoji::width(&emoji) + 1 + type_.len(),
+			(Some(EmojiMode::Replace), Some(emoji)) => emoji::width(&emoji),
+			_ => type_.len(),
+		}
+	}
+
+	pub fn get_scope_description(&self, scope: &str) -> Option<&str> {
+		self.scopes.descriptions.get(scope).map(String::as_str)
+	}
+
+	pub fn get_scope_emoji(&self, scope: &str) -> Option<&str> {
+		self.scopes.emojis.get(scope).map(String::as_str)
+	}
+
+	pub fn get_type_paths(&self) -> &HashMap<String, String> {
+		&self.types.paths
+	}
+
+	pub fn get_scope_paths(&self) -> &HashMap<String, String> {
+		&self.scopes.paths
+	}
+
+	pub fn is_scope_strict(&self) -> bool {
+		self.scopes.strict.unwrap_or_default()
+	}
+
 	pub fn get_type_items(&self) -> &[String] {
 		self.types.items.as_slice()
 	}
 
+	/// Display name of a type, e.g. `Features` for `feat`
+	pub fn get_type_name<'a>(&'a self, type_: &'a str) -> &'a str {
+		self
+			.types
+			.names
+			.get(type_)
+			.map(String::as_str)
+			.or_else(|| Type::default_name(type_))
+			.unwrap_or(type_)
+	}
+
+	/// Config as if `content` was the resin.toml
+	#[cfg(test)]
+	pub fn from_toml(content: &str) -> Self {
+		Self::get_conf(vec![toml::from_str(content).unwrap()])
+	}
+
 	pub fn get() -> Result<Self> {
-		Ok(Self::get_conf(TOML::get()?))
+		let mut warnings = Vec::new();
+		let mut config = Self::get_conf(TOML::get(&mut warnings)?);
+		config.warnings.extend(warnings);
+		Ok(config)
 	}
 
 	fn merge(&mut self, config: Config) {
 		self.types.merge(config.types);
 		self.scopes.merge(config.scopes);
-		self.sign = self.sign || config.sign;
+		self.sign = self.sign.or(config.sign);
+		self.breaking_exclamation = self.breaking_exclamation.or(config.breaking_exclamation);
+		self.ticket.merge(config.ticket);
+		self.emoji.merge(config.emoji);
 	}
 
-	fn get_conf(toml: Option<TOML>) -> Self {
-		if toml.is_none() {
-			return Self::default();
-		}
-		let mut toml: Config = toml.unwrap().into();
+	/// Merges the layers, later ones are more specific and take precedence
+	fn get_conf(layers: Vec<TOML>) -> Self {
+		let layers: Vec<Config> = layers.into_iter().map(Config::from).collect();
 		let mut config = Config::default();
-		// we only want to capitalize the default values
-		if toml.types.capitalize {
+		// we only want to capitalize the default values, as told by the most specific layer
+		let capitalize_types = layers.iter().rev().find_map(|layer| layer.types.capitalize);
+		let capitalize_scopes = layers
+			.iter()
+			.rev()
+			.find_map(|layer| layer.scopes.capitalize);
+		if capitalize_types.unwrap_or_default() {
 			config.types.capitalize_self();
 		}
-		if toml.scopes.capitalize {
+		if capitalize_scopes.unwrap_or_default() {
 			config.scopes.capitalize_self();
 		}
 
-		toml.merge(config);
-		toml
+		// a layer only ignores items of itself and the layers below it,
+		// so a more specific layer can bring an ignored item back
+		for mut layer in layers {
+			let types_ignore = std::mem::take(&mut layer.types.ignore);
+			let scopes_ignore = std::mem::take(&mut layer.scopes.ignore);
+			let warnings = std::mem::take(&mut config.warnings);
+			layer.merge(config);
+			layer.types.ignore = types_ignore;
+			layer.scopes.ignore = scopes_ignore;
+			layer.warnings = warnings;
+			// ignoring is done after merging so the capitalization doesn't matter
+			for (name, items) in [("types", &mut layer.types), ("scopes", &mut layer.scopes)] {
+				for unmatched in items.apply_ignore() {
+					layer.warnings.push(format!(
+						"Ignored entry `{unmatched}` in {name} doesn't match any known item"
+					));
+				}
+			}
+			config = layer;
+		}
+		config
 	}
 }
 
@@ -119,17 +322,47 @@ impl From<TOML> for Config {
 		Self {
 			types: val.types.into(),
 			scopes: val.scopes.into(),
-			sign: val.sign.unwrap_or_default(),
+			sign: val.sign,
+			breaking_exclamation: val.breaking_exclamation,
+			ticket: val.ticket.into(),
+			emoji: val.emoji.into(),
+			warnings: Vec::new(),
 		}
 	}
 }
 
 impl From<TOMLItemConfig> for ItemConfig {
 	fn from(val: TOMLItemConfig) -> Self {
+		let mut items = Vec::new();
+		let mut descriptions = HashMap::new();
+		let mut emojis = HashMap::new();
+		for item in val.items.unwrap_or_default() {
+			match item {
+				TOMLItem::Name(name) => items.push(name),
+				TOMLItem::Table {
+					name,
+					description,
+					emoji,
+				} => {
+					if let Some(description) = description {
+						descriptions.insert(name.clone(), description);
+					}
+					if let Some(emoji) = emoji {
+						emojis.insert(name.clone(), emoji);
+					}
+					items.push(name);
+				}
+			}
+		}
 		Self {
-			items: Self::option_vec_helper(val.items),
-			capitalize: val.capitalize.unwrap_or_default(),
+			items,
+			capitalize: val.capitalize,
 			ignore: Self::option_vec_helper(val.ignore),
+			strict: val.strict,
+			names: val.names.unwrap_or_default(),
+			paths: val.paths.unwrap_or_default(),
+			descriptions,
+			emojis,
 		}
 	}
 }
@@ -143,3 +376,167 @@ impl From<Option<TOMLItemConfig>> for ItemConfig {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::{
+		super::old,
+		*,
+	};
+
+	fn conf(content: &str) -> Config {
+		Config::from_toml(content)
+	}
+
+	#[test]
+	fn test_ignore_default() {
+		let config = conf("[types]\nignore = ['style', 'revert']");
+		let mut expected = Type::get_vec();
+		expected.retain(|item| item != "style" && item != "revert");
+		assert_eq!(config.get_type_items(), expected);
+		assert_eq!(config.get_scope_items(), Scope::get_vec());
+	}
+
+	#[test]
+	fn test_ignore_capitalized() {
+		let config = conf("[types]\ncapitalize = true\nignore = ['style']");
+		assert!(config.get_type_items().contains(&"Feat".into()));
+		assert!(!config.get_type_items().contains(&"Style".into()));
+
+		let config = conf("[types]\ncapitalize = true\nignore = ['Style']");
+		assert!(!config.get_type_items().contains(&"Style".into()));
+	}
+
+	#[test]
+	fn test_ignore_own_items() {
+		let config = conf("[scopes]\nitems = ['conf', 'git']\nignore = ['git', 'theme']");
+		assert_eq!(config.get_scope_items()[0], "conf");
+		assert!(!config.get_scope_items().contains(&"git".into()));
+		assert!(!config.get_scope_items().contains(&"theme".into()));
+	}
+
+	#[test]
+	fn test_unmatched_ignore() {
+		let mut items = ItemConfig {
+			items: vec!["feat".into(), "fix".into()],
+			ignore: vec!["FIX".into(), "nope".into()],
+			..Default::default()
+		};
+		assert_eq!(items.apply_ignore(), vec![String::from("nope")]);
+		assert_eq!(items.items, vec![String::from("feat")]);
+
+		let config = conf("[types]\nignore = ['nope']");
+		assert_eq!(
+			config.get_warnings(),
+			["Ignored entry `nope` in types doesn't match any known item"]
+		);
+	}
+
+	#[test]
+	fn test_merge_extends_ignore() {
+		let mut items = ItemConfig {
+			ignore: vec!["style".into()],
+			..Default::default()
+		};
+		items.merge(ItemConfig {
+			ignore: vec!["revert".into()],
+			capitalize: Some(true),
+			..Default::default()
+		});
+		assert_eq!(items.ignore, vec![String::from("style"), "revert".into()]);
+		assert_eq!(items.capitalize, Some(true));
+	}
+
+	#[test]
+	fn test_old_format() {
+		let old: old::TOML = toml::from_str("change_types = ['wip']\nsign = true").unwrap();
+		let config = Config::get_conf(vec![old.into()]);
+		assert_eq!(config.get_type_items()[0], "wip");
+		assert_eq!(config.get_type_items()[1..], Type::get_vec());
+		assert_eq!(config.get_scope_items(), Scope::get_vec());
+		assert!(config.get_signoff());
+	}
+
+	#[test]
+	fn test_type_paths() {
+		let config = conf("[types.paths]\n'*.md' = 'chore'\n'*.snap' = 'test'");
+		assert_eq!(config.get_type_paths()["*.md"], "chore");
+		assert_eq!(config.get_type_paths()["*.snap"], "test");
+		assert_eq!(config.get_type_paths()["Cargo.lock"], "build");
+	}
+
+	#[test]
+	fn test_item_tables() {
+		let config = conf(
+			"[types]\nitems = ['wip', { name = 'perf', description = 'Faster', emoji = '⚡' \
+			 }]\n[scopes]\nitems = [{ name = 'conf', description = 'resin.toml' }]",
+		);
+		assert_eq!(config.get_type_items()[..2], ["wip", "perf"]);
+		// perf isn't listed twice
+		assert_eq!(config.get_type_items().len(), Type::get_vec().len() + 1);
+		assert_eq!(config.get_type_description("perf"), Some("Faster"));
+		assert_eq!(config.get_type_emoji("perf"), Some("⚡"));
+		assert_eq!(config.get_type_description("fix"), Some("A bug fix"));
+		assert_eq!(config.get_type_description("wip"), None);
+		assert_eq!(config.get_scope_description("conf"), Some("resin.toml"));
+		assert_eq!(config.get_scope_emoji("conf"), None);
+	}
+
+	#[test]
+	fn test_emoji() {
+		let config = conf("[types]\nitems = [{ name = 'wip', emoji = '🚧' }]");
+		assert_eq!(config.get_type_emoji("feat"), None);
+		assert_eq!(config.get_type_emoji("wip"), Some("🚧"));
+
+		let config = conf(
+			"[emoji]\nmode = 'replace'\nformat = 'shortcode'\n[types]\nitems = [{ name = 'wip', emoji = \
+			 '🚧' }]",
+		);
+		assert_eq!(config.get_type_emoji("feat"), Some("✨"));
+		assert_eq!(config.resolve_type("feat"), Some("feat"));
+		assert_eq!(config.resolve_type(":sparkles:"), Some("feat"));
+		assert_eq!(config.resolve_type("♻"), Some("refactor"));
+		assert_eq!(config.resolve_type(":construction:"), Some("wip"));
+		assert_eq!(config.resolve_type("🦀"), None);
+		// `:recycle:` is 9 chars wide
+		assert_eq!(config.type_header_len("refactor"), 9);
+	}
+
+	#[test]
+	fn test_layers() {
+		let user: TOML = toml::from_str(
+			"sign = true\n[scopes]\nitems = ['home']\nstrict = true\n[ticket]\nkey = 'Closes'",
+		)
+		.unwrap();
+		let repo: TOML =
+			toml::from_str("sign = false\n[scopes]\nitems = ['conf']\nstrict = false").unwrap();
+		let package: TOML = toml::from_str("[scopes]\nitems = ['parser']").unwrap();
+		let config = Config::get_conf(vec![user, repo, package]);
+		// flags are overridden by the more specific layer
+		assert!(!config.get_signoff());
+		assert!(!config.is_scope_strict());
+		// lists are extended, the more specific items first
+		assert_eq!(config.get_scope_items()[..3], ["parser", "conf", "home"]);
+		// unset values fall through
+		assert_eq!(config.get_ticket().key.as_deref(), Some("Closes"));
+	}
+
+	#[test]
+	fn test_layers_ignore() {
+		let user: TOML = toml::from_str("[types]\nignore = ['chore', 'style']").unwrap();
+		let repo: TOML = toml::from_str("[types]\nitems = ['chore']").unwrap();
+		let config = Config::get_conf(vec![user, repo]);
+		// the repository lists `chore` again, `style` stays ignored
+		assert!(config.get_type_items().contains(&"chore".into()));
+		assert!(!config.get_type_items().contains(&"style".into()));
+
+		let user: TOML = toml::from_str("[types]\ncapitalize = true").unwrap();
+		let repo: TOML = toml::from_str("[types]\nignore = ['chore']").unwrap();
+		let config = Config::get_conf(vec![user, repo]);
+		assert!(config.get_type_items().contains(&"Feat".into()));
+		assert!(!config.get_type_items().contains(&"Chore".into()));
+		assert!(config.get_warnings().is_empty());
+	}
+}
@@ -0,0 +1,89 @@
+use resin::emoji::{
+	to_shortcode,
+	to_unicode,
+};
+use serde::Deserialize;
+
+/// Where the gitmoji of the type goes
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiMode {
+	/// `✨ feat: description`
+	Prefix,
+	/// `✨: description`
+	Replace,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiFormat {
+	/// `✨`
+	#[default]
+	Unicode,
+	/// `:sparkles:`
+	Shortcode,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub struct TOMLEmojiConfig {
+	pub mode: Option<EmojiMode>,
+	pub format: Option<EmojiFormat>,
+}
+
+/// Emojis are off unless a mode is set
+#[derive(Debug, Default, PartialEq)]
+pub struct EmojiConfig {
+	pub mode: Option<EmojiMode>,
+	pub format: Option<EmojiFormat>,
+}
+
+impl EmojiConfig {
+	pub fn get_mode(&self) -> Option<EmojiMode> {
+		self.mode
+	}
+
+	/// Converts `emoji` into the configured form, unknown emojis are kept as they are
+	pub fn format(&self, emoji: &str) -> String {
+		let converted = match self.format.unwrap_or_default() {
+			EmojiFormat::Unicode => to_unicode(emoji),
+			EmojiFormat::Shortcode => to_shortcode(emoji),
+		};
+		converted.unwrap_or(emoji).into()
+	}
+
+	pub fn merge(&mut self, merge: EmojiConfig) {
+		self.mode = self.mode.or(merge.mode);
+		self.format = self.format.or(merge.format);
+	}
+}
+
+impl From<Option<TOMLEmojiConfig>> for EmojiConfig {
+	fn from(val: Option<TOMLEmojiConfig>) -> Self {
+		let val = val.unwrap_or_default();
+		Self {
+			mode: val.mode,
+			format: val.format,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn test_format() {
+		let config = EmojiConfig::default();
+		assert_eq!(config.format(":sparkles:"), "✨");
+		assert_eq!(config.format("🦀"), "🦀");
+
+		let config = EmojiConfig {
+			format: Some(EmojiFormat::Shortcode),
+			..Default::default()
+		};
+		assert_eq!(config.format("✨"), ":sparkles:");
+		assert_eq!(config.format(":custom:"), ":custom:");
+	}
+}
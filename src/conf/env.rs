@@ -0,0 +1,178 @@
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use toml::{
+	Table,
+	Value,
+};
+
+use super::toml::TOML;
+
+enum Kind {
+	Bool,
+	/// Comma separated
+	List,
+	String,
+}
+
+/// `RESIN_*` variable -> key in resin.toml
+static VARIABLES: [(&str, &[&str], Kind); 12] = [
+	("RESIN_SIGN", &["sign"], Kind::Bool),
+	(
+		"RESIN_BREAKING_EXCLAMATION",
+		&["breaking_exclamation"],
+		Kind::Bool,
+	),
+	("RESIN_TYPES", &["types", "items"], Kind::List),
+	("RESIN_TYPES_IGNORE", &["types", "ignore"], Kind::List),
+	("RESIN_SCOPES", &["scopes", "items"], Kind::List),
+	("RESIN_SCOPES_IGNORE", &["scopes", "ignore"], Kind::List),
+	("RESIN_SCOPES_STRICT", &["scopes", "strict"], Kind::Bool),
+	(
+		"RESIN_TICKET_PLACEMENT",
+		&["ticket", "placement"],
+		Kind::String,
+	),
+	("RESIN_TICKET_KEY", &["ticket", "key"], Kind::String),
+	("RESIN_TICKET_FORMAT", &["ticket", "format"], Kind::String),
+	("RESIN_EMOJI_MODE", &["emoji", "mode"], Kind::String),
+	("RESIN_EMOJI_FORMAT", &["emoji", "format"], Kind::String),
+];
+
+fn parse(name: &str, value: &str, kind: &Kind) -> Result<Value> {
+	Ok(match kind {
+		Kind::Bool => match value.to_ascii_lowercase().as_str() {
+			"1" | "true" | "yes" | "on" => Value::Boolean(true),
+			"0" | "false" | "no" | "off" => Value::Boolean(false),
+			_ => bail!("{name} has to be true or false, not `{value}`"),
+		},
+		Kind::List => Value::Array(
+			value
+				.split(',')
+				.map(str::trim)
+				.filter(|item| !item.is_empty())
+				.map(|item| Value::String(item.into()))
+				.collect(),
+		),
+		Kind::String => Value::String(value.into()),
+	})
+}
+
+/// Puts `value` at `keys` into `table`, creating the parent tables
+fn insert(table: &mut Table, keys: &[&str], value: Value) {
+	let (key, parents) = keys.split_last().unwrap();
+	let mut current = table;
+	for parent in parents {
+		current = current
+			.entry(*parent)
+			.or_insert_with(|| Value::Table(Table::new()))
+			.as_table_mut()
+			.unwrap();
+	}
+	current.insert((*key).into(), value);
+}
+
+/// The value of a single variable, checked on its own so a typo only drops that variable
+fn check(name: &str, value: &str, keys: &[&str], kind: &Kind) -> Result<Value> {
+	let parsed = parse(name, value, kind)?;
+	let mut table = Table::new();
+	insert(&mut table, keys, parsed.clone());
+	if let Err(e) = Value::Table(table).try_into::<TOML>() {
+		bail!("{name} can't be `{value}`, {}", e.message());
+	}
+	Ok(parsed)
+}
+
+/// The most specific layer, `None` if no valid variable is set
+///
+/// Invalid variables are skipped and reported in `warnings`
+pub fn from_env(
+	vars: impl Iterator<Item = (String, String)>,
+	warnings: &mut Vec<String>,
+) -> Result<Option<TOML>> {
+	let mut table = Table::new();
+	for (name, value) in vars {
+		let Some((_, keys, kind)) = VARIABLES.iter().find(|(variable, ..)| *variable == name) else {
+			continue;
+		};
+		match check(&name, &value, keys, kind) {
+			Ok(value) => insert(&mut table, keys, value),
+			Err(e) => warnings.push(format!("{e:#}, ignoring the variable")),
+		}
+	}
+	if table.is_empty() {
+		return Ok(None);
+	}
+	let toml = Value::Table(table)
+		.try_into()
+		.context("Invalid RESIN_* environment variable")?;
+	Ok(Some(toml))
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+	use crate::conf::Placement;
+
+	fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+		vars
+			.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect::<Vec<_>>()
+			.into_iter()
+	}
+
+	#[test]
+	fn test_from_env() {
+		let mut warnings = Vec::new();
+		let toml = from_env(
+			vars(&[
+				("RESIN_SIGN", "yes"),
+				("RESIN_SCOPES", "conf, git,"),
+				("RESIN_TICKET_PLACEMENT", "trailer"),
+				("PATH", "/usr/bin"),
+			]),
+			&mut warnings,
+		)
+		.unwrap()
+		.unwrap();
+		assert!(warnings.is_empty());
+		assert_eq!(toml.sign, Some(true));
+		assert_eq!(
+			toml.scopes.unwrap().items.unwrap(),
+			vec![String::from("conf").into(), String::from("git").into()]
+		);
+		assert_eq!(toml.ticket.unwrap().placement, Some(Placement::Trailer));
+	}
+
+	#[test]
+	fn test_from_env_errors() {
+		let mut warnings = Vec::new();
+		assert!(from_env(vars(&[("HOME", "/root")]), &mut warnings)
+			.unwrap()
+			.is_none());
+		assert!(warnings.is_empty());
+
+		let toml = from_env(
+			vars(&[
+				("RESIN_SIGN", "maybe"),
+				("RESIN_EMOJI_MODE", "sideways"),
+				("RESIN_SCOPES_STRICT", "true"),
+			]),
+			&mut warnings,
+		)
+		.unwrap()
+		.unwrap();
+		// only the broken variables are skipped
+		assert_eq!(toml.sign, None);
+		assert_eq!(toml.emoji, None);
+		assert_eq!(toml.scopes.unwrap().strict, Some(true));
+		assert_eq!(warnings.len(), 2);
+		assert!(warnings[0].starts_with("RESIN_SIGN"));
+		assert!(warnings[1].starts_with("RESIN_EMOJI_MODE"));
+	}
+}
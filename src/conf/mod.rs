@@ -1,7 +1,15 @@
 #[allow(clippy::module_inception)]
 mod conf;
 pub use conf::Config;
+mod emoji;
+pub use emoji::EmojiMode;
+mod env;
 mod old;
 mod scope;
+mod ticket;
+pub use ticket::{
+	Placement,
+	TicketConfig,
+};
 mod toml;
 mod type_;
@@ -41,20 +41,21 @@ impl TOML {
 // when I remove old::TOML
 impl From<TOML> for NewTOML {
 	fn from(toml: TOML) -> Self {
-		let change_types = toml.change_types.map(|v| v.into_iter().collect());
-		let scopes = toml.scopes.map(|v| v.into_iter().collect());
+		let change_types = toml
+			.change_types
+			.map(|v| v.into_iter().map(Into::into).collect());
+		let scopes = toml.scopes.map(|v| v.into_iter().map(Into::into).collect());
 		Self {
 			types: Some(TOMLItemConfig {
 				items: change_types,
-				capitalize: None,
-				ignore: None,
+				..Default::default()
 			}),
 			scopes: Some(TOMLItemConfig {
 				items: scopes,
-				capitalize: None,
-				ignore: None,
+				..Default::default()
 			}),
 			sign: toml.sign,
+			..Default::default()
 		}
 	}
 }
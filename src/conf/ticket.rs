@@ -0,0 +1,416 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use resin::{
+	Footer,
+	Separator,
+};
+use serde::Deserialize;
+
+use crate::utils::output_failure;
+
+static PLACEHOLDER: &str = "{ticket}";
+static JIRA: OnceLock<Regex> = OnceLock::new();
+static ID: OnceLock<Regex> = OnceLock::new();
+
+/// Words followed by a number in branch names that are no Jira project
+static NOT_KEYS: [&str; 14] = [
+	"feat", "feature", "fix", "bugfix", "hotfix", "release", "chore", "docs", "test", "v", "utf",
+	"sha", "iso", "x86",
+];
+
+/// Used when no patterns are configured, matches Jira keys like `AB-12`
+///
+/// Branch names are often lowercase, so the case is ignored and the key uppercased
+fn get_jira_regex() -> &'static Regex {
+	JIRA.get_or_init(|| Regex::new(r"(?i)\b[A-Z][A-Z0-9_]+-[0-9]+\b").unwrap())
+}
+
+/// A whole ticket id as written without a format: `PROJ-12` or `#12`
+fn get_id_regex() -> &'static Regex {
+	ID.get_or_init(|| Regex::new("^(?:[A-Z][A-Z0-9_]+-[0-9]+|#[0-9]+)$").unwrap())
+}
+
+/// Where tickets end up in the commit message
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+	/// Prefix of the description: `feat: PROJ-1 description`
+	Header,
+	/// First line of the body
+	#[default]
+	Body,
+	/// Git trailer next to `Signed-off-by`: `Refs: PROJ-1`
+	Trailer,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub struct TOMLTicketConfig {
+	pub placement: Option<Placement>,
+	/// Trailer key, only used with `placement = "trailer"`
+	pub key: Option<String>,
+	/// Template where `{ticket}` is replaced by the ticket id, e.g. `#{ticket}`
+	pub format: Option<String>,
+	/// Tried in order against the branch name
+	pub patterns: Option<Vec<TOMLTicketPattern>>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct TOMLTicketPattern {
+	pub name: String,
+	pub regex: String,
+	/// `{group}` is replaced by the named capture group, defaults to the whole match
+	pub template: Option<String>,
+	pub uppercase: Option<bool>,
+}
+
+#[derive(Debug)]
+pub struct TicketPattern {
+	pub regex: Regex,
+	pub template: Option<String>,
+	/// Linear branches are lowercase while the ids are not
+	pub uppercase: bool,
+}
+
+impl TicketPattern {
+	pub fn find(&self, haystack: &str) -> Option<String> {
+		let captures = self.regex.captures(haystack)?;
+		let ticket = match &self.template {
+			Some(template) => {
+				self
+					.regex
+					.capture_names()
+					.flatten()
+					.fold(template.clone(), |ticket, name| {
+						let value = captures.name(name).map_or("", |value| value.as_str());
+						ticket.replace(&format!("{{{name}}}"), value)
+					})
+			}
+			None => captures[0].into(),
+		};
+		Some(if self.uppercase {
+			ticket.to_uppercase()
+		} else {
+			ticket
+		})
+	}
+}
+
+impl TryFrom<TOMLTicketPattern> for TicketPattern {
+	type Error = regex::Error;
+
+	fn try_from(val: TOMLTicketPattern) -> Result<Self, Self::Error> {
+		Ok(Self {
+			regex: Regex::new(&val.regex)?,
+			template: val.template,
+			uppercase: val.uppercase.unwrap_or_default(),
+		})
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct TicketConfig {
+	pub placement: Option<Placement>,
+	pub key: Option<String>,
+	pub format: Option<String>,
+	pub patterns: Vec<TicketPattern>,
+}
+
+impl TicketConfig {
+	pub fn get_placement(&self) -> Placement {
+		self.placement.unwrap_or_default()
+	}
+
+	pub fn get_key(&self) -> &str {
+		self.key.as_deref().unwrap_or("Refs")
+	}
+
+	pub fn format(&self, ticket: &str) -> String {
+		match &self.format {
+			Some(format) => format.replace(PLACEHOLDER, ticket),
+			None => ticket.into(),
+		}
+	}
+
+	/// First ticket any pattern finds in the branch name
+	pub fn detect(&self, branch: &str) -> Option<String> {
+		if self.patterns.is_empty() {
+			return get_jira_regex()
+				.find_iter(branch)
+				.map(|ticket| ticket.as_str())
+				.find(|ticket| {
+					let key = ticket.rsplit_once('-').map_or("", |(key, _)| key);
+					!NOT_KEYS.contains(&key.to_ascii_lowercase().as_str())
+				})
+				.map(str::to_uppercase);
+		}
+		self
+			.patterns
+			.iter()
+			.find_map(|pattern| pattern.find(branch))
+	}
+
+	/// The ticket if `text` is exactly what `format` turns a ticket into
+	fn parse(&self, text: &str) -> Option<String> {
+		let affixes = self
+			.format
+			.as_deref()
+			.and_then(|format| format.split_once(PLACEHOLDER))
+			.filter(|(prefix, suffix)| !prefix.is_empty() || !suffix.is_empty());
+		let ticket = match affixes {
+			Some((prefix, suffix)) => text.strip_prefix(prefix)?.strip_suffix(suffix)?,
+			None => get_id_regex().find(text)?.as_str(),
+		};
+		(!ticket.is_empty() && !ticket.contains(char::is_whitespace)).then(|| ticket.into())
+	}
+
+	/// Tickets of an existing message, only looked for where `placement` puts them
+	/// so prose like `utf-8` in the body isn't taken for one
+	pub fn parse_message(&self, description: &str, body: Option<&str>) -> Vec<String> {
+		match self.get_placement() {
+			Placement::Header => description
+				.split(' ')
+				.map_while(|word| self.parse(word))
+				.collect(),
+			Placement::Body => body
+				.unwrap_or_default()
+				.lines()
+				.map_while(|line| self.parse(line.trim()))
+				.collect(),
+			Placement::Trailer => Vec::new(),
+		}
+	}
+
+	/// Prefix of the description for `placement = "header"`
+	pub fn header_prefix(&self, tickets: &[String]) -> Option<String> {
+		if self.get_placement() != Placement::Header || tickets.is_empty() {
+			return None;
+		}
+		let tickets: Vec<String> = tickets.iter().map(|ticket| self.format(ticket)).collect();
+		Some(tickets.join(" "))
+	}
+
+	/// Lines at the start of the body for `placement = "body"`
+	pub fn body_lines(&self, tickets: &[String]) -> Vec<String> {
+		if self.get_placement() != Placement::Body {
+			return Vec::new();
+		}
+		tickets.iter().map(|ticket| self.format(ticket)).collect()
+	}
+
+	/// Trailers for `placement = "trailer"`
+	pub fn trailers(&self, tickets: &[String]) -> Vec<Footer> {
+		if self.get_placement() != Placement::Trailer {
+			return Vec::new();
+		}
+		tickets
+			.iter()
+			.map(|ticket| {
+				let value = self.format(ticket);
+				// `Closes #42` instead of `Closes: #42`
+				match value.strip_prefix('#') {
+					Some(issue) => Footer {
+						token: self.get_key().into(),
+						separator: Separator::Hash,
+						value: issue.into(),
+					},
+					None => Footer::new(self.get_key(), value),
+				}
+			})
+			.collect()
+	}
+
+	pub fn merge(&mut self, merge: TicketConfig) {
+		self.placement = self.placement.or(merge.placement);
+		self.key = self.key.take().or(merge.key);
+		self.format = self.format.take().or(merge.format);
+		if self.patterns.is_empty() {
+			self.patterns = merge.patterns;
+		}
+	}
+}
+
+impl From<Option<TOMLTicketConfig>> for TicketConfig {
+	fn from(val: Option<TOMLTicketConfig>) -> Self {
+		let val = val.unwrap_or_default();
+		let patterns = val
+			.patterns
+			.unwrap_or_default()
+			.into_iter()
+			.filter_map(|pattern| {
+				let name = pattern.name.clone();
+				// a broken pattern shouldn't stop you from committing
+				TicketPattern::try_from(pattern)
+					.inspect_err(|e| output_failure(&format!("Invalid ticket pattern {name}: {e}")))
+					.ok()
+			})
+			.collect();
+		Self {
+			placement: val.placement,
+			key: val.key,
+			format: val.format,
+			patterns,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn tickets() -> Vec<String> {
+		vec!["PROJ-1".into(), "PROJ-2".into()]
+	}
+
+	#[test]
+	fn test_body() {
+		let config = TicketConfig::default();
+		assert_eq!(config.body_lines(&tickets()), tickets());
+		assert_eq!(config.header_prefix(&tickets()), None);
+		assert!(config.trailers(&tickets()).is_empty());
+	}
+
+	#[test]
+	fn test_header() {
+		let config = TicketConfig {
+			placement: Some(Placement::Header),
+			format: Some("[{ticket}]".into()),
+			..Default::default()
+		};
+		assert_eq!(
+			config.header_prefix(&tickets()).as_deref(),
+			Some("[PROJ-1] [PROJ-2]")
+		);
+		assert!(config.body_lines(&tickets()).is_empty());
+	}
+
+	#[test]
+	fn test_trailer() {
+		let config = TicketConfig {
+			placement: Some(Placement::Trailer),
+			..Default::default()
+		};
+		assert_eq!(
+			config.trailers(&tickets()),
+			vec![Footer::new("Refs", "PROJ-1"), Footer::new("Refs", "PROJ-2")]
+		);
+
+		let config = TicketConfig {
+			placement: Some(Placement::Trailer),
+			key: Some("Closes".into()),
+			format: Some("#{ticket}".into()),
+			..Default::default()
+		};
+		let trailers = config.trailers(&["42".into()]);
+		assert_eq!(trailers[0].to_string(), "Closes #42");
+	}
+
+	fn patterns(patterns: &str) -> TicketConfig {
+		let toml: TOMLTicketConfig = toml::from_str(patterns).unwrap();
+		Some(toml).into()
+	}
+
+	#[test]
+	fn test_detect_jira() {
+		let config = TicketConfig::default();
+		assert_eq!(config.detect("Proj-123"), Some("PROJ-123".into()));
+		assert_eq!(config.detect("feature/AB-12-login"), Some("AB-12".into()));
+		assert_eq!(config.detect("feature/ab-12-login"), Some("AB-12".into()));
+		assert_eq!(config.detect("fix-123/PROJ-4-login"), Some("PROJ-4".into()));
+		assert_eq!(config.detect("main"), None);
+	}
+
+	#[test]
+	fn test_detect_jira_false_positives() {
+		let config = TicketConfig::default();
+		for branch in [
+			"release-2024",
+			"fix-123",
+			"feature/utf-8-paths",
+			"chore/sha-256",
+			"docs/iso-8601-dates",
+			"build/x86-64",
+			"feature/v-2",
+			"feature/ab12-login",
+			"PROJ-12x",
+		] {
+			assert_eq!(config.detect(branch), None, "{branch}");
+		}
+	}
+
+	#[test]
+	fn test_detect_github() {
+		let config = patterns(
+			r##"[[patterns]]
+name = "github"
+regex = '^(?:[^/]+/)?(?<number>[0-9]+)-'
+template = "#{number}""##,
+		);
+		assert_eq!(config.detect("123-fix-login"), Some("#123".into()));
+		assert_eq!(config.detect("fix/123-login"), Some("#123".into()));
+		assert_eq!(config.detect("fix-login"), None);
+	}
+
+	#[test]
+	fn test_detect_gitlab() {
+		let config = patterns(
+			r##"[[patterns]]
+name = "gitlab"
+regex = '^(?<iid>[0-9]+)-'
+template = "!{iid}""##,
+		);
+		assert_eq!(config.detect("45-update-readme"), Some("!45".into()));
+	}
+
+	#[test]
+	fn test_detect_linear() {
+		let config = patterns(
+			r##"[[patterns]]
+name = "linear"
+regex = '(?<team>[a-z]+)-(?<number>[0-9]+)'
+template = "{team}-{number}"
+uppercase = true"##,
+		);
+		assert_eq!(
+			config.detect("feature/eng-1234-foo"),
+			Some("ENG-1234".into())
+		);
+	}
+
+	#[test]
+	fn test_detect_azure() {
+		let config = patterns(
+			r##"[[patterns]]
+name = "azure"
+regex = '(?i)ab#?(?<id>[0-9]+)'
+template = "AB#{id}""##,
+		);
+		assert_eq!(
+			config.detect("users/jane/ab123-login"),
+			Some("AB#123".into())
+		);
+	}
+
+	#[test]
+	fn test_detect_in_order() {
+		let config = patterns(
+			r##"[[patterns]]
+name = "broken"
+regex = '('
+
+[[patterns]]
+name = "jira"
+regex = '[A-Z]{2,}-[0-9]+'
+
+[[patterns]]
+name = "github"
+regex = '^(?<number>[0-9]+)-'
+template = "#{number}""##,
+		);
+		assert_eq!(config.patterns.len(), 2);
+		assert_eq!(config.detect("12-PROJ-3"), Some("PROJ-3".into()));
+		assert_eq!(config.detect("12-fix"), Some("#12".into()));
+	}
+}
@@ -1,4 +1,5 @@
 use std::{
+	collections::HashMap,
 	env::current_dir,
 	fs,
 	path::{
@@ -7,25 +8,59 @@ use std::{
 	},
 };
 
+use directories::BaseDirs;
 use serde::Deserialize;
 
-use super::old;
+use super::{
+	emoji::TOMLEmojiConfig,
+	env,
+	old,
+	ticket::TOMLTicketConfig,
+};
 use crate::utils::{
 	output_failure,
 	output_info,
 };
 
-static FILENAME: &str = "resin.toml";
+/// Checked in this order, only the first one of a directory is read
+static FILENAMES: [&str; 2] = ["resin.toml", ".resin.toml"];
+/// Manifests that can hold the config in `[tool.resin]`
+static MANIFESTS: [&str; 2] = ["Cargo.toml", "pyproject.toml"];
+static USER_FILENAME: &str = "config.toml";
 
+/// Either a plain `"perf"` or a table with some help for newcomers
 #[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum TOMLItem {
+	Name(String),
+	Table {
+		name: String,
+		description: Option<String>,
+		emoji: Option<String>,
+	},
+}
+
+impl From<String> for TOMLItem {
+	fn from(name: String) -> Self {
+		Self::Name(name)
+	}
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
 pub struct TOMLItemConfig {
-	pub items: Option<Vec<String>>,
+	pub items: Option<Vec<TOMLItem>>,
 	// At work I was outvoted and I had to include this
 	pub capitalize: Option<bool>,
 	pub ignore: Option<Vec<String>>,
+	/// Only allow values from `items`
+	pub strict: Option<bool>,
+	/// Display names, e.g. the changelog headings
+	pub names: Option<HashMap<String, String>>,
+	/// Glob of changed files -> item that is suggested for them
+	pub paths: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq)]
 // somehow writing Toml feels wrong
 // I mean its an acronym so either all upper or all lowercase
 #[allow(clippy::upper_case_acronyms)]
@@ -35,6 +70,11 @@ pub struct TOML {
 	pub types: Option<TOMLItemConfig>,
 	pub scopes: Option<TOMLItemConfig>,
 	pub sign: Option<bool>,
+	/// Mark breaking changes with `!` in the header
+	pub breaking_exclamation: Option<bool>,
+	pub ticket: Option<TOMLTicketConfig>,
+	/// Gitmoji in the header, off by default
+	pub emoji: Option<TOMLEmojiConfig>,
 }
 
 impl TOML {
@@ -78,17 +118,161 @@ impl TOML {
 		output_info("Falling back to defaults");
 	}
 
-	/// Reading config file
-	pub fn get() -> anyhow::Result<Option<Self>> {
-		let current_dir = current_dir()?;
-		let ancestors = current_dir.ancestors();
-		for ancestor in ancestors {
-			let path = ancestor.join(FILENAME);
+	/// Every config layer, from the most general to the most specific:
+	/// user config, repository root, nested directories and `RESIN_*` variables
+	///
+	/// Invalid `RESIN_*` variables are skipped and reported in `warnings`
+	pub fn get(warnings: &mut Vec<String>) -> anyhow::Result<Vec<Self>> {
+		let mut layers = Vec::new();
+		if let Some(path) = user_config() {
+			if path.exists() {
+				layers.push(Self::read(&path)?);
+			}
+		}
+		layers.extend(Self::get_project(&current_dir()?)?);
+		if let Some(env) = env::from_env(std::env::vars(), warnings)? {
+			layers.push(env);
+		}
+		Ok(layers)
+	}
+
+	/// Configs from the repository root down to `dir`,
+	/// so packages of a monorepo can have their own
+	fn get_project(dir: &Path) -> anyhow::Result<Vec<Self>> {
+		let Some(root) = dir
+			.ancestors()
+			.find(|ancestor| ancestor.join(".git").exists())
+		else {
+			// outside of a repository only the nearest config is used
+			for ancestor in dir.ancestors() {
+				if let Some(toml) = Self::find_in(ancestor)? {
+					return Ok(vec![toml]);
+				}
+			}
+			return Ok(Vec::new());
+		};
+		let mut dirs: Vec<&Path> = dir
+			.ancestors()
+			.take_while(|ancestor| *ancestor != root)
+			.collect();
+		dirs.push(root);
+		let mut layers = Vec::new();
+		for dir in dirs.into_iter().rev() {
+			layers.extend(Self::find_in(dir)?);
+		}
+		Ok(layers)
+	}
+
+	/// The config of a single directory, the first file that exists wins
+	fn find_in(dir: &Path) -> anyhow::Result<Option<Self>> {
+		for filename in FILENAMES {
+			let path = dir.join(filename);
 			if path.exists() {
 				return Ok(Some(Self::read(&path)?));
 			}
 		}
-		// if no config was found just use defaults
+		for manifest in MANIFESTS {
+			let path = dir.join(manifest);
+			if path.exists() {
+				if let Some(toml) = Self::read_manifest(&path)? {
+					return Ok(Some(toml));
+				}
+			}
+		}
 		Ok(None)
 	}
+
+	/// `[tool.resin]` of a Cargo.toml or pyproject.toml
+	fn read_manifest(path: &Path) -> anyhow::Result<Option<Self>> {
+		let content = fs::read_to_string(path)?;
+		// a broken manifest is not our business
+		let Ok(manifest) = content.parse::<toml::Table>() else {
+			return Ok(None);
+		};
+		let table = manifest
+			.get("tool")
+			.and_then(|tool| tool.get("resin"))
+			// cargo warns about unknown keys outside of `metadata`
+			.or_else(|| {
+				["package", "workspace"]
+					.iter()
+					.find_map(|key| manifest.get(*key)?.get("metadata")?.get("resin"))
+			});
+		let Some(table) = table else {
+			return Ok(None);
+		};
+		let toml = table
+			.clone()
+			.try_into()
+			.inspect_err(|_| Self::print_err(path))?;
+		Ok(Some(toml))
+	}
+}
+
+/// `$XDG_CONFIG_HOME/resin/config.toml` or whatever the platform prefers
+fn user_config() -> Option<PathBuf> {
+	BaseDirs::new().map(|dirs| dirs.config_dir().join("resin").join(USER_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+
+	fn sign(toml: &TOML) -> Option<bool> {
+		toml.sign
+	}
+
+	#[test]
+	fn test_project_layers() {
+		let dir = TempDir::new().unwrap();
+		let root = dir.path();
+		let package = root.join("crates/parser/src");
+		fs::create_dir_all(root.join(".git")).unwrap();
+		fs::create_dir_all(&package).unwrap();
+		fs::write(root.join(".resin.toml"), "sign = true").unwrap();
+		fs::write(
+			root.join("crates/parser/Cargo.toml"),
+			"[package]\nname = 'parser'\n[package.metadata.resin]\nsign = false",
+		)
+		.unwrap();
+		fs::write(root.join("crates/Cargo.toml"), "not toml at all [").unwrap();
+
+		let layers = TOML::get_project(&package).unwrap();
+		assert_eq!(
+			layers.iter().map(sign).collect::<Vec<_>>(),
+			[Some(true), Some(false)]
+		);
+	}
+
+	#[test]
+	fn test_project_precedence() {
+		let dir = TempDir::new().unwrap();
+		let root = dir.path();
+		fs::create_dir_all(root.join(".git")).unwrap();
+		fs::write(root.join("resin.toml"), "sign = true").unwrap();
+		fs::write(root.join(".resin.toml"), "sign = false").unwrap();
+		fs::write(root.join("pyproject.toml"), "[tool.resin]\nsign = false").unwrap();
+		// resin.toml wins over the other files in the same directory
+		let layers = TOML::get_project(root).unwrap();
+		assert_eq!(layers.iter().map(sign).collect::<Vec<_>>(), [Some(true)]);
+
+		fs::remove_file(root.join("resin.toml")).unwrap();
+		fs::remove_file(root.join(".resin.toml")).unwrap();
+		let layers = TOML::get_project(root).unwrap();
+		assert_eq!(layers.iter().map(sign).collect::<Vec<_>>(), [Some(false)]);
+	}
+
+	#[test]
+	fn test_outside_repository() {
+		let dir = TempDir::new().unwrap();
+		let nested = dir.path().join("a/b");
+		fs::create_dir_all(&nested).unwrap();
+		fs::write(dir.path().join("resin.toml"), "sign = true").unwrap();
+		fs::write(dir.path().join("a/resin.toml"), "sign = false").unwrap();
+		let layers = TOML::get_project(&nested).unwrap();
+		assert_eq!(layers.iter().map(sign).collect::<Vec<_>>(), [Some(false)]);
+	}
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use strum::{
 	Display,
 	EnumIter,
@@ -26,4 +28,95 @@ impl Type {
 	pub fn get_vec() -> Vec<String> {
 		Self::iter().map(|val| val.to_string()).collect()
 	}
+
+	/// Headings used by the changelog
+	pub fn default_name(type_: &str) -> Option<&'static str> {
+		let name = match type_.to_ascii_lowercase().as_str() {
+			"feat" => "Features",
+			"fix" => "Bug Fixes",
+			"docs" => "Documentation",
+			"style" => "Styles",
+			"refactor" => "Code Refactoring",
+			"chore" => "Chores",
+			"build" => "Build System",
+			"ci" => "Continuous Integration",
+			"perf" => "Performance Improvements",
+			"test" => "Tests",
+			"revert" => "Reverts",
+			_ => return None,
+		};
+		Some(name)
+	}
+
+	/// Shown next to the type in the selection
+	pub fn default_description(type_: &str) -> Option<&'static str> {
+		let description = match type_.to_ascii_lowercase().as_str() {
+			"feat" => "A new feature",
+			"fix" => "A bug fix",
+			"docs" => "Documentation only changes",
+			"style" => {
+				"Changes that do not affect the meaning of the code (white-space, formatting, ...)"
+			}
+			"refactor" => "A code change that neither fixes a bug nor adds a feature",
+			"chore" => "Other changes that don't modify source or test files",
+			"build" => "Changes that affect the build system or external dependencies",
+			"ci" => "Changes to the CI configuration files and scripts",
+			"perf" => "A code change that improves performance",
+			"test" => "Adding missing tests or correcting existing tests",
+			"revert" => "Reverts a previous commit",
+			_ => return None,
+		};
+		Some(description)
+	}
+
+	/// Gitmoji of the type, used when the emoji mode is on
+	pub fn default_emoji(type_: &str) -> Option<&'static str> {
+		let emoji = match type_.to_ascii_lowercase().as_str() {
+			"feat" => "✨",
+			"fix" => "🐛",
+			"docs" => "📝",
+			"style" => "🎨",
+			"refactor" => "♻️",
+			"chore" => "🔧",
+			"build" => "📦",
+			"ci" => "👷",
+			"perf" => "⚡",
+			"test" => "✅",
+			"revert" => "⏪",
+			_ => return None,
+		};
+		Some(emoji)
+	}
+
+	/// Files that only ever belong to one type, used to suggest it
+	pub fn default_paths() -> HashMap<String, String> {
+		let paths: [(&str, Self); 22] = [
+			("*.md", Self::docs),
+			("docs/", Self::docs),
+			("tests/", Self::test),
+			("**/tests/", Self::test),
+			("*_test.go", Self::test),
+			("*.test.ts", Self::test),
+			("*.spec.ts", Self::test),
+			("test_*.py", Self::test),
+			(".github/workflows/", Self::ci),
+			(".gitlab-ci.yml", Self::ci),
+			(".circleci/", Self::ci),
+			("Cargo.toml", Self::build),
+			("Cargo.lock", Self::build),
+			("build.rs", Self::build),
+			("package.json", Self::build),
+			("package-lock.json", Self::build),
+			("yarn.lock", Self::build),
+			("pnpm-lock.yaml", Self::build),
+			("go.mod", Self::build),
+			("go.sum", Self::build),
+			("pyproject.toml", Self::build),
+			("poetry.lock", Self::build),
+		];
+		paths
+			.into_iter()
+			.map(|(glob, type_)| (glob.into(), type_.to_string()))
+			.collect()
+	}
 }
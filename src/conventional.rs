@@ -0,0 +1,429 @@
+use std::{
+	error::Error,
+	fmt::{
+		self,
+		Display,
+	},
+	str::FromStr,
+	sync::OnceLock,
+};
+
+use regex::Regex;
+
+use crate::emoji;
+
+static HEADER: OnceLock<Regex> = OnceLock::new();
+static FOOTER: OnceLock<Regex> = OnceLock::new();
+
+fn get_header_regex() -> &'static Regex {
+	HEADER.get_or_init(|| {
+		// gitmoji headers either put the emoji before the type or use it as the type
+		Regex::new(&format!(
+			concat!(
+				r"^(?:(?<emoji>{emoji}) )?(?<type>{emoji}|[^()!:\s]+)",
+				r"(?:\((?<scope>[^()]*)\))?(?<breaking>!)?:(?: (?<description>.*))?$",
+			),
+			emoji = emoji::PATTERN,
+		))
+		.unwrap()
+	})
+}
+
+fn get_footer_regex() -> &'static Regex {
+	FOOTER.get_or_init(|| {
+		Regex::new(r"^(?<token>BREAKING[ -]CHANGE|[A-Za-z0-9-]+)(?<separator>: | #)(?<value>.*)$")
+			.unwrap()
+	})
+}
+
+/// A commit message following <https://www.conventionalcommits.org/en/v1.0.0/>
+///
+/// ```
+/// use resin::ConventionalCommit;
+///
+/// let message = "feat(conf)!: drop the old format\n\nBREAKING CHANGE: old configs are ignored";
+/// let commit: ConventionalCommit = message.parse().unwrap();
+/// assert_eq!(commit.type_, "feat");
+/// assert!(commit.is_breaking());
+/// assert_eq!(commit.to_string(), message);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConventionalCommit {
+	/// Gitmoji in front of the type, `:sparkles:` or `✨`
+	pub emoji: Option<String>,
+	pub type_: String,
+	pub scope: Option<String>,
+	/// `!` right before the `:` in the header
+	pub breaking: bool,
+	pub description: String,
+	pub body: Option<String>,
+	pub footers: Vec<Footer>,
+}
+
+/// A git trailer like `Refs: #123` or `BREAKING CHANGE: ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footer {
+	pub token: String,
+	pub separator: Separator,
+	/// Can span multiple lines
+	pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Separator {
+	/// `token: value`
+	#[default]
+	Colon,
+	/// `token #value`
+	Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+	Empty,
+	InvalidHeader,
+	EmptyScope,
+	EmptyDescription,
+	MissingBlankLine,
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let message = match self {
+			Self::Empty => "Commit message is empty",
+			Self::InvalidHeader => "Header has to follow the format `type(scope)!: description`",
+			Self::EmptyScope => "Scope is empty",
+			Self::EmptyDescription => "Description is empty",
+			Self::MissingBlankLine => "Header and body have to be separated by an empty line",
+		};
+		f.write_str(message)
+	}
+}
+
+impl Error for ParseError {}
+
+impl ConventionalCommit {
+	pub fn new(type_: impl Into<String>, description: impl Into<String>) -> Self {
+		Self {
+			type_: type_.into(),
+			description: description.into(),
+			..Default::default()
+		}
+	}
+
+	/// Either marked with `!` or containing a `BREAKING CHANGE` footer
+	pub fn is_breaking(&self) -> bool {
+		self.breaking || self.footers.iter().any(Footer::is_breaking)
+	}
+
+	/// The first line of the message
+	pub fn header(&self) -> String {
+		let scope = self
+			.scope
+			.as_ref()
+			.map(|scope| format!("({scope})"))
+			.unwrap_or_default();
+		let emoji = self
+			.emoji
+			.as_ref()
+			.map(|emoji| format!("{emoji} "))
+			.unwrap_or_default();
+		let exclamation = if self.breaking { "!" } else { "" };
+		format!(
+			"{emoji}{}{scope}{exclamation}: {}",
+			self.type_, self.description
+		)
+	}
+
+	/// Values of every footer with `token` (compared case-insensitively)
+	pub fn footer_values<'a>(&'a self, token: &'a str) -> impl Iterator<Item = &'a str> {
+		self
+			.footers
+			.iter()
+			.filter(move |footer| footer.token.eq_ignore_ascii_case(token))
+			.map(|footer| footer.value.as_str())
+	}
+
+	pub fn parse(message: &str) -> Result<Self, ParseError> {
+		let lines: Vec<&str> = message.trim_end().lines().map(str::trim_end).collect();
+		let Some(header) = lines.first().filter(|header| !header.is_empty()) else {
+			return Err(ParseError::Empty);
+		};
+		let mut commit = Self::parse_header(header)?;
+		if lines.len() == 1 {
+			return Ok(commit);
+		}
+		if !lines[1].is_empty() {
+			return Err(ParseError::MissingBlankLine);
+		}
+
+		let rest = &lines[2..];
+		// footers live in the last paragraph
+		let paragraph_start = rest
+			.iter()
+			.rposition(|line| line.is_empty())
+			.map_or(0, |blank| blank + 1);
+		let (body, footers) = if rest
+			.get(paragraph_start)
+			.is_some_and(|line| get_footer_regex().is_match(line))
+		{
+			rest.split_at(paragraph_start)
+		} else {
+			(rest, &[][..])
+		};
+		let body = body.join("\n");
+		let body = body.trim_matches('\n');
+		commit.body = (!body.is_empty()).then(|| body.into());
+		commit.footers = Footer::parse_all(footers);
+		Ok(commit)
+	}
+
+	fn parse_header(header: &str) -> Result<Self, ParseError> {
+		let captures = get_header_regex()
+			.captures(header)
+			.ok_or(ParseError::InvalidHeader)?;
+		let scope = captures.name("scope").map(|scope| scope.as_str());
+		if scope == Some("") {
+			return Err(ParseError::EmptyScope);
+		}
+		let description = captures
+			.name("description")
+			.map_or("", |description| description.as_str());
+		if description.trim().is_empty() {
+			return Err(ParseError::EmptyDescription);
+		}
+		Ok(Self {
+			emoji: captures.name("emoji").map(|emoji| emoji.as_str().into()),
+			type_: captures["type"].into(),
+			scope: scope.map(Into::into),
+			breaking: captures.name("breaking").is_some(),
+			description: description.into(),
+			..Default::default()
+		})
+	}
+}
+
+impl FromStr for ConventionalCommit {
+	type Err = ParseError;
+
+	fn from_str(message: &str) -> Result<Self, Self::Err> {
+		Self::parse(message)
+	}
+}
+
+impl Display for ConventionalCommit {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.header())?;
+		if let Some(body) = &self.body {
+			write!(f, "\n\n{body}")?;
+		}
+		for (i, footer) in self.footers.iter().enumerate() {
+			let separator = if i == 0 { "\n\n" } else { "\n" };
+			write!(f, "{separator}{footer}")?;
+		}
+		Ok(())
+	}
+}
+
+impl Footer {
+	pub fn new(token: impl Into<String>, value: impl Into<String>) -> Self {
+		Self {
+			token: token.into(),
+			separator: Separator::Colon,
+			value: value.into(),
+		}
+	}
+
+	pub fn is_breaking(&self) -> bool {
+		self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+	}
+
+	/// Lines that don't start a new footer continue the previous one
+	fn parse_all(lines: &[&str]) -> Vec<Self> {
+		let mut footers: Vec<Self> = Vec::new();
+		for line in lines {
+			if let Some(captures) = get_footer_regex().captures(line) {
+				footers.push(Self {
+					token: captures["token"].into(),
+					separator: match &captures["separator"] {
+						" #" => Separator::Hash,
+						_ => Separator::Colon,
+					},
+					value: captures["value"].into(),
+				});
+			} else if let Some(footer) = footers.last_mut() {
+				footer.value.push('\n');
+				footer.value.push_str(line);
+			}
+		}
+		footers
+	}
+}
+
+impl Display for Footer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let separator = match self.separator {
+			Separator::Colon => ": ",
+			Separator::Hash => " #",
+		};
+		write!(f, "{}{separator}{}", self.token, self.value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn test_header() {
+		let commit = ConventionalCommit::parse("feat(conf)!: add parser").unwrap();
+		assert_eq!(
+			commit,
+			ConventionalCommit {
+				emoji: None,
+				type_: "feat".into(),
+				scope: Some("conf".into()),
+				breaking: true,
+				description: "add parser".into(),
+				body: None,
+				footers: vec![],
+			}
+		);
+		assert_eq!(
+			ConventionalCommit::parse("docs: readme").unwrap(),
+			ConventionalCommit::new("docs", "readme")
+		);
+	}
+
+	#[test]
+	fn test_emoji_header() {
+		let commit = ConventionalCommit::parse("✨ feat(conf): add emoji").unwrap();
+		assert_eq!(commit.emoji.as_deref(), Some("✨"));
+		assert_eq!(commit.type_, "feat");
+		assert_eq!(commit.scope.as_deref(), Some("conf"));
+
+		let commit = ConventionalCommit::parse(":sparkles: feat: add emoji").unwrap();
+		assert_eq!(commit.emoji.as_deref(), Some(":sparkles:"));
+		assert_eq!(commit.type_, "feat");
+
+		// the emoji replaces the type
+		let commit = ConventionalCommit::parse(":recycle:(git)!: split commit").unwrap();
+		assert_eq!(commit.emoji, None);
+		assert_eq!(commit.type_, ":recycle:");
+		assert!(commit.breaking);
+		assert_eq!(
+			ConventionalCommit::parse("♻️: split commit").unwrap().type_,
+			"♻️"
+		);
+	}
+
+	#[test]
+	fn test_body_and_footers() {
+		let message = "fix: prevent racing of requests
+
+Introduce a request id and a reference to latest request.
+
+Remove timeouts which were used to mitigate the racing issue.
+
+Reviewed-by: Z
+Refs #123
+BREAKING CHANGE: the timeout option is gone
+and has no replacement";
+		let commit = ConventionalCommit::parse(message).unwrap();
+		assert_eq!(
+			commit.body.as_deref(),
+			Some(
+				"Introduce a request id and a reference to latest request.\n\nRemove timeouts which were \
+				 used to mitigate the racing issue."
+			)
+		);
+		assert_eq!(
+			commit.footers,
+			vec![
+				Footer::new("Reviewed-by", "Z"),
+				Footer {
+					token: "Refs".into(),
+					separator: Separator::Hash,
+					value: "123".into(),
+				},
+				Footer::new(
+					"BREAKING CHANGE",
+					"the timeout option is gone\nand has no replacement"
+				),
+			]
+		);
+		assert!(!commit.breaking);
+		assert!(commit.is_breaking());
+		assert_eq!(commit.footer_values("refs").collect::<Vec<_>>(), ["123"]);
+	}
+
+	#[test]
+	fn test_footers_only() {
+		let commit = ConventionalCommit::parse("chore: bump\n\nSigned-off-by: A <a@b.c>\n").unwrap();
+		assert_eq!(commit.body, None);
+		assert_eq!(
+			commit.footers,
+			vec![Footer::new("Signed-off-by", "A <a@b.c>")]
+		);
+	}
+
+	#[test]
+	fn test_body_looks_like_footer() {
+		// only the last paragraph can contain footers
+		let commit =
+			ConventionalCommit::parse("chore: bump\n\nNote: this is body\n\nmore body").unwrap();
+		assert_eq!(
+			commit.body.as_deref(),
+			Some("Note: this is body\n\nmore body")
+		);
+		assert!(commit.footers.is_empty());
+	}
+
+	#[test]
+	fn test_errors() {
+		assert_eq!(ConventionalCommit::parse(""), Err(ParseError::Empty));
+		assert_eq!(
+			ConventionalCommit::parse("add parser"),
+			Err(ParseError::InvalidHeader)
+		);
+		assert_eq!(
+			ConventionalCommit::parse("feat:add parser"),
+			Err(ParseError::InvalidHeader)
+		);
+		assert_eq!(
+			ConventionalCommit::parse("feat(): add parser"),
+			Err(ParseError::EmptyScope)
+		);
+		assert_eq!(
+			ConventionalCommit::parse("feat: "),
+			Err(ParseError::EmptyDescription)
+		);
+		assert_eq!(
+			ConventionalCommit::parse("feat: add parser\nbody"),
+			Err(ParseError::MissingBlankLine)
+		);
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let messages = [
+			"feat: add parser",
+			"feat(lint)!: use the parser",
+			"fix: typo\n\nbody\n\nmore body",
+			"fix: typo\n\nbody\n\nRefs #1\nBREAKING CHANGE: first\nsecond",
+			"chore: release\n\nSigned-off-by: A <a@b.c>",
+			"🐛 fix(git): unborn HEAD",
+			":zap:: faster",
+		];
+		for message in messages {
+			let commit = ConventionalCommit::parse(message).unwrap();
+			assert_eq!(commit.to_string(), message);
+			assert_eq!(
+				ConventionalCommit::parse(&commit.to_string()).unwrap(),
+				commit
+			);
+		}
+	}
+}
@@ -0,0 +1,121 @@
+//! [gitmoji](https://gitmoji.dev/) shortcodes and their unicode forms
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+static EMOJI: OnceLock<Regex> = OnceLock::new();
+
+/// A `:shortcode:` or a unicode emoji (with variation selectors and joiners)
+pub(crate) const PATTERN: &str =
+	r":[a-z0-9_+-]+:|\p{Extended_Pictographic}[\x{FE0F}\x{200D}\p{Extended_Pictographic}]*";
+
+/// `(shortcode, unicode)` of the gitmojis that can be converted
+static GITMOJI: [(&str, &str); 30] = [
+	(":sparkles:", "✨"),
+	(":bug:", "🐛"),
+	(":memo:", "📝"),
+	(":art:", "🎨"),
+	(":recycle:", "♻️"),
+	(":wrench:", "🔧"),
+	(":package:", "📦"),
+	(":construction_worker:", "👷"),
+	(":zap:", "⚡"),
+	(":white_check_mark:", "✅"),
+	(":rewind:", "⏪"),
+	(":ambulance:", "🚑"),
+	(":lipstick:", "💄"),
+	(":boom:", "💥"),
+	(":fire:", "🔥"),
+	(":rocket:", "🚀"),
+	(":lock:", "🔒"),
+	(":bookmark:", "🔖"),
+	(":heavy_plus_sign:", "➕"),
+	(":heavy_minus_sign:", "➖"),
+	(":arrow_up:", "⬆️"),
+	(":arrow_down:", "⬇️"),
+	(":green_heart:", "💚"),
+	(":pencil2:", "✏️"),
+	(":truck:", "🚚"),
+	(":tada:", "🎉"),
+	(":construction:", "🚧"),
+	(":rotating_light:", "🚨"),
+	(":wastebasket:", "🗑️"),
+	(":globe_with_meridians:", "🌐"),
+];
+
+fn get_emoji_regex() -> &'static Regex {
+	EMOJI.get_or_init(|| Regex::new(&format!("^(?:{PATTERN})$")).unwrap())
+}
+
+/// `♻️` and `♻` are the same emoji, one just asks for the colorful presentation
+fn strip_selector(emoji: &str) -> String {
+	emoji.replace('\u{FE0F}', "")
+}
+
+fn find(emoji: &str) -> Option<&'static (&'static str, &'static str)> {
+	let stripped = strip_selector(emoji);
+	GITMOJI
+		.iter()
+		.find(|(shortcode, unicode)| *shortcode == emoji || strip_selector(unicode) == stripped)
+}
+
+/// Either form of an emoji
+///
+/// ```
+/// assert!(resin::emoji::is_emoji(":sparkles:"));
+/// assert!(resin::emoji::is_emoji("✨"));
+/// assert!(!resin::emoji::is_emoji("feat"));
+/// ```
+pub fn is_emoji(text: &str) -> bool {
+	get_emoji_regex().is_match(text)
+}
+
+/// `:sparkles:` -> `✨`, `None` for unknown shortcodes
+pub fn to_unicode(emoji: &str) -> Option<&'static str> {
+	find(emoji).map(|(_, unicode)| *unicode)
+}
+
+/// `✨` -> `:sparkles:`, `None` for emojis that aren't gitmojis
+pub fn to_shortcode(emoji: &str) -> Option<&'static str> {
+	find(emoji).map(|(shortcode, _)| *shortcode)
+}
+
+/// Compares emojis regardless of their form
+pub fn matches(a: &str, b: &str) -> bool {
+	match (find(a), find(b)) {
+		(Some(a), Some(b)) => a == b,
+		_ => strip_selector(a) == strip_selector(b),
+	}
+}
+
+/// Columns `text` takes up in a header, unicode emojis are two wide
+pub fn width(text: &str) -> usize {
+	if is_emoji(text) && !text.starts_with(':') {
+		2
+	} else {
+		text.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_convert() {
+		assert_eq!(to_unicode(":sparkles:"), Some("✨"));
+		assert_eq!(to_unicode("✨"), Some("✨"));
+		assert_eq!(to_shortcode("♻"), Some(":recycle:"));
+		assert_eq!(to_shortcode("🦀"), None);
+		assert!(matches(":bug:", "🐛"));
+		assert!(matches("🦀", "🦀"));
+		assert!(!matches(":bug:", ":sparkles:"));
+	}
+
+	#[test]
+	fn test_width() {
+		assert_eq!(width("feat"), 4);
+		assert_eq!(width("♻️"), 2);
+		assert_eq!(width(":recycle:"), 9);
+	}
+}
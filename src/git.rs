@@ -1,31 +1,61 @@
 use std::{
-	path::Path,
+	env,
+	fs,
+	io::{
+		stdin,
+		IsTerminal,
+	},
+	path::{
+		Path,
+		PathBuf,
+	},
 	process::exit,
 };
 
-use anyhow::Result;
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
 use clap::ArgMatches;
 use git2::{
 	Commit,
 	Config,
 	Diff,
+	DiffFindOptions,
+	DiffStatsFormat,
+	ErrorCode,
 	Index,
 	IndexAddOption,
+	ObjectType,
 	Oid,
-	Reference,
 	Repository,
+	RepositoryState,
 	Signature,
+	Status,
+	StatusOptions,
+	Time,
 	Tree,
 };
+use resin::{
+	ConventionalCommit,
+	Footer,
+};
 
 use super::utils::fail;
 use crate::{
+	conf,
+	hook::run_git_hook,
 	inputs::{
 		ask_for_path,
 		prompt_for_path,
+		select_files,
 		Inputs,
 	},
+	push::push,
+	sign::Signer,
 	utils::{
+		output_failure,
 		output_info,
 		output_success,
 	},
@@ -39,20 +69,71 @@ pub fn get_repo() -> Repository {
 	repo.unwrap()
 }
 
-pub fn commit_changes(signoff: bool, args: &ArgMatches, inputs: &Inputs) -> Result<()> {
+pub fn commit_changes(args: &ArgMatches, message: &str) -> Result<()> {
 	let repo = get_repo();
-	if args.get_one::<bool>("all").is_some() {
-		add_all(&mut get_index(&repo));
+	let message = if args.get_flag("no-verify") {
+		message.to_string()
 	} else {
-		check_emptiness(&repo)
+		match verify_message(&repo, message) {
+			Ok(message) => message,
+			Err(e) => {
+				output_failure(&format!("{e:#}"));
+				exit(1);
+			}
+		}
 	};
-	let signoff = if signoff {
-		format_signoff(&get_signatures(&repo)).unwrap_or_default()
+	commit(&repo, &message);
+	// like with git, a failing post-commit hook doesn't undo the commit
+	if let Err(e) = run_git_hook(&repo, "post-commit", &[]) {
+		output_failure(&format!("{e:#}"));
+	}
+	if args.get_flag("push") {
+		if let Err(e) = push(&repo) {
+			output_failure(&format!("Failed to push: {e:#}"));
+			exit(1);
+		}
+	}
+	Ok(())
+}
+
+/// Runs the `pre-commit` hook, before the prompts so nobody writes a message for nothing
+pub fn verify_staged(repo: &Repository) -> Result<()> {
+	run_git_hook(repo, "pre-commit", &[])
+}
+
+/// Runs the `commit-msg` hook, which may rewrite the message in `COMMIT_EDITMSG`
+pub fn verify_message(repo: &Repository, message: &str) -> Result<String> {
+	let path = repo.path().join("COMMIT_EDITMSG");
+	fs::write(&path, with_newline(message))
+		.with_context(|| format!("Failed to write {}", path.display()))?;
+	run_git_hook(repo, "commit-msg", &[&path])?;
+	Ok(fs::read_to_string(&path)?)
+}
+
+/// git terminates every message with a newline
+fn with_newline(message: &str) -> String {
+	format!("{}\n", message.trim_end_matches('\n'))
+}
+
+/// The full commit message including the optional signoff
+pub fn gen_message(repo: &Repository, config: &conf::Config, inputs: &Inputs) -> String {
+	let signoff = if config.get_signoff() {
+		format_signoff(&get_signatures(repo))
 	} else {
-		String::new()
+		None
 	};
-	commit(&repo, &gen_commit_msg(inputs, signoff));
-	Ok(())
+	let emoji = config.get_emoji().get_mode().zip(
+		config
+			.get_type_emoji(inputs.change_type)
+			.map(|emoji| config.get_emoji().format(emoji)),
+	);
+	gen_commit_msg(
+		inputs,
+		config.get_breaking_exclamation(),
+		config.get_ticket(),
+		emoji,
+		signoff,
+	)
 }
 
 // Im handling the err ... don't know why rustc complains
@@ -64,26 +145,54 @@ fn add(index: &mut Index, path: &Path) {
 	index.write().map_err(fail);
 }
 
+/// `git add -A`
 // Im handling the err ... don't know why rustc complains
 #[allow(unused_must_use)]
 fn add_all(index: &mut Index) {
 	index
 		.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
 		.map_err(fail);
+	// `add_all` doesn't know about files that are gone
+	index.update_all(["*"].iter(), None).map_err(fail);
 	index.write().map_err(fail);
 }
 
+/// `git add -u`
+// Im handling the err ... don't know why rustc complains
+#[allow(unused_must_use)]
+fn update_all(index: &mut Index) {
+	index.update_all(["*"].iter(), None).map_err(fail);
+	index.write().map_err(fail);
+}
+
+/// Stages the changes requested with `--all` or `--update`
+pub fn stage_requested(repo: &Repository, args: &ArgMatches) {
+	if args.get_flag("all") {
+		add_all(&mut get_index(repo));
+	} else if args.get_flag("update") {
+		update_all(&mut get_index(repo));
+	}
+}
+
 /// Check if there are is anything in staging
 /// if not we should offer to specify a path or exit
 pub fn check_emptiness(repo: &Repository) {
-	let mut index = get_index(repo);
 	let err_msg = "Your staging area is empty";
 	if is_empty(repo) {
 		output_info(err_msg);
+		// there is nobody to ask for a path
+		if !stdin().is_terminal() {
+			exit(1)
+		}
+		pick_files(repo);
+		if !is_empty(repo) {
+			return;
+		}
 		if !ask_for_path() {
 			exit(0)
 		}
 		let path = prompt_for_path();
+		let mut index = get_index(repo);
 		add(&mut index, path.as_path());
 		// if the index is still empty... just fail
 		if is_empty(repo) {
@@ -92,21 +201,137 @@ pub fn check_emptiness(repo: &Repository) {
 	}
 }
 
-fn get_head_tree(repo: &Repository) -> Tree {
-	let head = get_head(repo).peel_to_tree();
+/// A changed file as shown by `git status --short`
+#[derive(Debug, PartialEq)]
+struct FileStatus {
+	path: PathBuf,
+	/// Status letter in the index, `' '` if unchanged
+	index: char,
+	/// Status letter in the working tree, `' '` if unchanged
+	worktree: char,
+}
+
+impl FileStatus {
+	fn label(&self) -> String {
+		format!("{}{} {}", self.index, self.worktree, self.path.display())
+	}
+}
+
+fn index_letter(status: Status) -> char {
+	match status {
+		s if s.contains(Status::INDEX_NEW) => 'A',
+		s if s.contains(Status::INDEX_MODIFIED) => 'M',
+		s if s.contains(Status::INDEX_DELETED) => 'D',
+		s if s.contains(Status::INDEX_RENAMED) => 'R',
+		s if s.contains(Status::INDEX_TYPECHANGE) => 'T',
+		s if s.contains(Status::WT_NEW) => '?',
+		_ => ' ',
+	}
+}
+
+fn worktree_letter(status: Status) -> char {
+	match status {
+		s if s.contains(Status::WT_NEW) => '?',
+		s if s.contains(Status::WT_MODIFIED) => 'M',
+		s if s.contains(Status::WT_DELETED) => 'D',
+		s if s.contains(Status::WT_RENAMED) => 'R',
+		s if s.contains(Status::WT_TYPECHANGE) => 'T',
+		_ => ' ',
+	}
+}
+
+/// Modified, deleted and untracked files, staged or not
+fn get_statuses(repo: &Repository) -> Result<Vec<FileStatus>> {
+	let mut options = StatusOptions::new();
+	options
+		.include_untracked(true)
+		.recurse_untracked_dirs(true)
+		.include_ignored(false);
+	let statuses = repo.statuses(Some(&mut options))?;
+	Ok(
+		statuses
+			.iter()
+			.filter(|entry| !entry.status().is_conflicted())
+			.filter_map(|entry| {
+				Some(FileStatus {
+					path: entry.path()?.into(),
+					index: index_letter(entry.status()),
+					worktree: worktree_letter(entry.status()),
+				})
+			})
+			.collect(),
+	)
+}
+
+/// Stages the selected files, only called while the index is empty
+fn stage_selection(repo: &Repository, files: &[FileStatus], selected: &[usize]) -> Result<()> {
+	let mut index = repo.index()?;
+	for file in selected.iter().filter_map(|i| files.get(*i)) {
+		if file.worktree == 'D' {
+			index.remove_path(&file.path)?;
+		} else {
+			index.add_path(&file.path)?;
+		}
+	}
+	index.write()?;
+	Ok(())
+}
+
+fn pick_files(repo: &Repository) {
+	let files = match get_statuses(repo) {
+		Ok(files) => files,
+		Err(e) => return fail(format!("read the status: {e}")),
+	};
+	if files.is_empty() {
+		return;
+	}
+	let labels: Vec<String> = files.iter().map(FileStatus::label).collect();
+	let selected = select_files(&labels);
+	if let Err(e) = stage_selection(repo, &files, &selected) {
+		fail(format!("stage the files: {e}"));
+	}
+}
+
+/// `git diff --cached --stat` including renames
+pub fn get_diffstat(repo: &Repository) -> Result<String> {
+	let mut diff = get_diff(repo);
+	diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+	let stats = diff.stats()?;
+	let buffer = stats.to_buf(DiffStatsFormat::FULL | DiffStatsFormat::INCLUDE_SUMMARY, 80)?;
+	Ok(buffer.as_str().unwrap_or_default().into())
+}
+
+/// Paths of the staged files, deleted files included
+pub fn get_staged_paths(repo: &Repository) -> Vec<PathBuf> {
+	get_diff(repo)
+		.deltas()
+		.filter_map(|delta| {
+			delta
+				.new_file()
+				.path()
+				.or_else(|| delta.old_file().path())
+				.map(PathBuf::from)
+		})
+		.collect()
+}
+
+/// `None` before the first commit
+fn get_head_tree(repo: &Repository) -> Option<Tree<'_>> {
+	let head = get_parent(repo)?.tree();
 	if let Err(ref e) = head {
 		fail(e);
 	}
-	head.unwrap()
+	head.ok()
 }
 
 fn is_empty(repo: &Repository) -> bool {
 	get_diff(repo).deltas().len() == 0
 }
 
-fn get_diff(repo: &Repository) -> Diff {
+fn get_diff(repo: &Repository) -> Diff<'_> {
+	// without a HEAD everything in the index is new
 	let head = get_head_tree(repo);
-	let diff = repo.diff_tree_to_index(Some(&head), None, None);
+	let diff = repo.diff_tree_to_index(head.as_ref(), None, None);
 	if let Err(ref e) = diff {
 		fail(e);
 	}
@@ -121,31 +346,69 @@ fn get_index(repo: &Repository) -> Index {
 	index.unwrap()
 }
 
-fn get_signatures(repo: &Repository) -> Signature {
-	let sig = repo.signature();
+/// The committer, used for the signoff as well
+fn get_signatures(repo: &Repository) -> Signature<'static> {
+	let sig = get_identity(repo, "COMMITTER", |var| env::var(var).ok());
 	if let Err(ref e) = sig {
-		fail(e);
+		fail(format!("{e:#}"));
 	}
 	sig.unwrap()
 }
 
-fn get_head(repo: &Repository) -> Reference {
+/// `GIT_AUTHOR_*` or `GIT_COMMITTER_*` take precedence over `user.name` and `user.email`
+/// like they do for git, `repo.signature()` only knows the config
+fn get_identity(
+	repo: &Repository,
+	role: &str,
+	var: impl Fn(&str) -> Option<String>,
+) -> Result<Signature<'static>> {
+	let config = repo.config()?;
+	let get = |key: &str, config_key: &str| {
+		var(&format!("GIT_{role}_{key}"))
+			.or_else(|| config.get_string(config_key).ok())
+			.with_context(|| format!("{config_key} is not set"))
+	};
+	let name = get("NAME", "user.name")?;
+	let email = get("EMAIL", "user.email")?;
+	Ok(match var(&format!("GIT_{role}_DATE")) {
+		Some(date) => Signature::new(&name, &email, &parse_date(&date)?)?,
+		None => Signature::now(&name, &email)?,
+	})
+}
+
+/// git's internal format `<unix timestamp> <+hhmm>`, optionally with a leading `@`
+fn parse_date(date: &str) -> Result<Time> {
+	let date = date.trim().trim_start_matches('@');
+	let (seconds, offset) = date.split_once(' ').unwrap_or((date, "+0000"));
+	let invalid = || format!("Unsupported date `{date}`, use `<unix timestamp> <+hhmm>`");
+	let seconds: i64 = seconds.parse().with_context(invalid)?;
+	let sign = if offset.starts_with('-') { -1 } else { 1 };
+	let digits = offset.trim_start_matches(['+', '-']);
+	if digits.len() != 4 {
+		bail!(invalid());
+	}
+	let hours: i32 = digits[..2].parse().with_context(invalid)?;
+	let minutes: i32 = digits[2..].parse().with_context(invalid)?;
+	Ok(Time::new(seconds, sign * (hours * 60 + minutes)))
+}
+
+/// The commit HEAD points to, `None` on an unborn branch
+fn get_parent(repo: &Repository) -> Option<Commit<'_>> {
 	let head = repo.head();
 	if let Err(ref e) = head {
+		if e.code() == ErrorCode::UnbornBranch {
+			return None;
+		}
 		fail(e);
 	}
-	head.unwrap()
-}
-
-fn get_commit<'a>(ref_: &'a Reference<'a>) -> Commit<'a> {
-	let commit = ref_.peel_to_commit();
+	let commit = head.ok()?.peel_to_commit();
 	if let Err(ref e) = commit {
 		fail(e);
 	}
-	commit.unwrap()
+	commit.ok()
 }
 
-fn get_tree(repo: &Repository, oid: Oid) -> Tree {
+fn get_tree(repo: &Repository, oid: Oid) -> Tree<'_> {
 	let tree = repo.find_tree(oid);
 	if let Err(ref e) = tree {
 		fail(e);
@@ -169,99 +432,638 @@ fn get_config(repo: &Repository) -> Config {
 	config.unwrap()
 }
 
-fn should_sign(config: &Config) -> bool {
-	let gpgsign = config.get_bool("commit.gpgsign");
-	if let Err(ref e) = gpgsign {
-		fail(e);
+fn commit(repo: &Repository, message: &str) {
+	match create_commit(repo, message) {
+		Err(e) => fail(format!("commit changes: {e:#}")),
+		Ok(_) => output_success("Committed changes"),
 	}
-	gpgsign.unwrap()
 }
 
-fn get_signature(config: &Config) {
-	let gpgsign = config.get_str("user.signingkey");
+/// Builds the commit object in-process (instead of shelling out to `git commit`)
+/// and signs it if `commit.gpgsign` is set
+fn create_commit(repo: &Repository, message: &str) -> Result<Oid> {
+	let mut index = get_index(repo);
+	let tree = get_tree(repo, write_changes(&mut index));
+	commit_tree(repo, &tree, message)
 }
 
-fn commit(_repo: &Repository, message: &str) {
-	// Ok I'll shell out because of signing for now
-	// and commit with the git command
-	// which'll do everything automagically ...
-	let status = std::process::Command::new("git")
-		.args(["commit", "-m", message])
-		.status();
-	if status.is_err() || status.is_ok_and(|x| !x.success()) {
-		fail("failed to commit changes");
-	}
-	output_success("Committed changes");
+fn commit_tree(repo: &Repository, tree: &Tree, message: &str) -> Result<Oid> {
+	let author = get_identity(repo, "AUTHOR", |var| env::var(var).ok())?;
+	let committer = get_signatures(repo);
+	let message = with_newline(message);
+	let parent = get_parent(repo);
+	let merge_heads = get_merge_heads(repo)?;
+	let parents: Vec<&Commit> = parent.iter().chain(&merge_heads).collect();
+	// generate the commit object __WITHOUT__ writing it to the object db
+	let buffer = repo.commit_create_buffer(&author, &committer, &message, tree, &parents)?;
+	let content = buffer
+		.as_str()
+		.context("Commit buffer is not valid UTF-8")?;
+	let oid = match Signer::from_config(&get_config(repo), &committer)? {
+		Some(signer) => repo.commit_signed(content, &signer.sign(content)?, None)?,
+		None => repo.odb()?.write(ObjectType::Commit, content.as_bytes())?,
+	};
+	// the reflog tells what kind of commit it was, like git does
+	let action = match (&parent, merge_heads.is_empty()) {
+		(None, _) => "commit (initial)",
+		(Some(_), false) => "commit (merge)",
+		(Some(_), true) => "commit",
+	};
+	update_head(repo, oid, &message, action)?;
+	cleanup_state(repo)?;
+	Ok(oid)
+}
 
-	// to actually implement this without calling any cli command i'll have to
-	// - use commit_create_buffer to generate the commit object __WITHOUT__ writing it to the object db
-	// - convert the buff to a string
-	// - if commit.gpg = true
-	// - find user.signingkey
-	// - decide if gpg.format is unset or gpg then gpg otherwise ssh (x.509 is another bag of worms...)
-	// - sign that string with SshSig.sign in the ssh_key crate
-	// - sign that string with <something something pgp>
+/// The other side of a merge that is concluded by this commit
+fn get_merge_heads(repo: &Repository) -> Result<Vec<Commit<'_>>> {
+	if repo.state() != RepositoryState::Merge {
+		return Ok(Vec::new());
+	}
+	// `mergehead_foreach` wants a mutable repository just to read a file
+	let content = fs::read_to_string(repo.path().join("MERGE_HEAD"))?;
+	content
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| Ok(repo.find_commit(Oid::from_str(line.trim())?)?))
+		.collect()
+}
 
-	// let sig = get_signatures(repo);
-	// let head = get_head(repo);
-	// let parent = get_commit(&head);
-	// let mut index = get_index(repo);
-	// let oid = write_changes(&mut index);
-	// let tree = get_tree(repo, oid);
-	// let res = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent]);
-	// TODO: sign commit
-	// match res {
-	// Err(e) => fail(e),
-	// Ok(_) => output_success("Committed changes"),
-	// }
+/// Concludes a merge, cherry-pick or revert like `git commit` does
+fn cleanup_state(repo: &Repository) -> Result<()> {
+	match repo.state() {
+		RepositoryState::Merge | RepositoryState::CherryPick | RepositoryState::Revert => {
+			repo.cleanup_state()?
+		}
+		// `cleanup_state` would throw away the sequencer and the rest of the picks
+		RepositoryState::CherryPickSequence | RepositoryState::RevertSequence => {
+			for file in ["CHERRY_PICK_HEAD", "REVERT_HEAD", "MERGE_MSG"] {
+				let path = repo.path().join(file);
+				if path.exists() {
+					fs::remove_file(path)?;
+				}
+			}
+		}
+		_ => {}
+	}
+	Ok(())
 }
 
-pub fn get_branch_name(repo: &Repository) -> Option<String> {
-	let branch = get_head(repo);
-	branch.name().map(|val| val.into())
+/// Stages `paths` (relative to the workdir) and commits only them,
+/// whatever else is staged stays staged for the next commit
+pub fn commit_paths(repo: &Repository, paths: &[PathBuf], message: &str) -> Result<Oid> {
+	let mut index = get_index(repo);
+	// the tree of HEAD with nothing but `paths` changed
+	let mut partial = Index::new()?;
+	if let Some(tree) = get_head_tree(repo) {
+		partial.read_tree(&tree)?;
+	}
+	for path in paths {
+		index.add_path(path)?;
+		let entry = index
+			.get_path(path, 0)
+			.with_context(|| format!("Failed to stage {}", path.display()))?;
+		partial.add(&entry)?;
+	}
+	index.write()?;
+	let tree = repo.find_tree(partial.write_tree_to(repo)?)?;
+	commit_tree(repo, &tree, message)
 }
 
-fn format_scope(scope: &Option<String>) -> String {
-	match scope {
-		None => String::new(),
-		Some(scope) => format!("({scope})"),
+/// `commit_signed` only writes the object, so we have to move HEAD ourselves
+fn update_head(repo: &Repository, oid: Oid, message: &str, action: &str) -> Result<()> {
+	let summary = message.lines().next().unwrap_or_default();
+	let log_message = format!("{action}: {summary}");
+	let head = repo.find_reference("HEAD")?;
+	match head.symbolic_target() {
+		Some(branch) => {
+			repo.reference(branch, oid, true, &log_message)?;
+		}
+		None => repo.set_head_detached(oid)?,
 	}
+	Ok(())
 }
 
-fn gen_commit_msg(inputs: &Inputs, signoff: String) -> String {
+/// Short name of the checked out branch, e.g. `feature/ABC-12`
+///
+/// Also works on an unborn branch, during a rebase and in worktrees
+pub fn get_branch_name(repo: &Repository) -> Option<String> {
+	// `repo.head()` fails on an unborn branch, the symbolic ref is there anyway
+	let head = repo.find_reference("HEAD").ok()?;
+	let refname = match head.symbolic_target() {
+		Some(target) => target.to_string(),
+		// HEAD is detached while rebasing, git remembers the branch for afterwards
+		None => ["rebase-merge", "rebase-apply"]
+			.iter()
+			.find_map(|dir| fs::read_to_string(repo.path().join(dir).join("head-name")).ok())?
+			.trim()
+			.to_string(),
+	};
+	refname.strip_prefix("refs/heads/").map(Into::into)
+}
+
+fn gen_commit_msg(
+	inputs: &Inputs,
+	exclamation: bool,
+	ticket_config: &conf::TicketConfig,
+	emoji: Option<(conf::EmojiMode, String)>,
+	signoff: Option<Footer>,
+) -> String {
 	let Inputs {
 		change_type,
 		scope,
 		description,
 		long_description,
 		breaking_changes,
-		ticket,
+		tickets,
 	} = inputs;
-	let scope = format_scope(scope);
-	let exclamation = if breaking_changes.is_empty() { "" } else { "!" };
-	let ticket = if !ticket.is_empty() {
-		ticket.to_owned() + "\n"
-	} else {
-		ticket.to_owned()
+	let description = match ticket_config.header_prefix(tickets) {
+		Some(prefix) => format!("{prefix} {description}"),
+		None => description.clone(),
 	};
+	let mut commit = ConventionalCommit::new(*change_type, description);
+	commit.scope = scope.clone();
+	match emoji {
+		Some((conf::EmojiMode::Prefix, emoji)) => commit.emoji = Some(emoji),
+		Some((conf::EmojiMode::Replace, emoji)) => commit.type_ = emoji,
+		None => {}
+	}
 
-	format!(
-		"{change_type}{scope}{exclamation}: {description}
-{ticket}{long_description}\n
-{breaking_changes}\n
-{signoff}"
-	)
-	// breaking change and or signoff could be missing
-	.trim_end()
-	.into()
+	let mut body = ticket_config.body_lines(tickets);
+	if !long_description.trim().is_empty() {
+		body.push(long_description.trim().into());
+	}
+	let body = body.join("\n");
+	commit.body = (!body.is_empty()).then_some(body);
+
+	// an empty line would end the footer and a line like `Note: ...` would start a new one,
+	// so the lines after the first are indented like folded git trailers
+	let breaking_changes = breaking_changes
+		.lines()
+		.map(str::trim_end)
+		.filter(|line| !line.is_empty())
+		.collect::<Vec<_>>()
+		.join("\n  ");
+	if !breaking_changes.is_empty() {
+		commit.breaking = exclamation;
+		commit
+			.footers
+			.push(Footer::new("BREAKING CHANGE", breaking_changes));
+	}
+	// all trailers are grouped together in the last paragraph
+	commit.footers.extend(ticket_config.trailers(tickets));
+	commit.footers.extend(signoff);
+	commit.to_string()
 }
 
-fn format_signoff(signature: &Signature) -> Option<String> {
+fn format_signoff(signature: &Signature) -> Option<Footer> {
 	let name = signature.name();
 	let email = signature.email();
 	if let (Some(name), Some(email)) = (name, email) {
-		Some(format!("Signed-off-by: {name} <{email}>"))
+		Some(Footer::new("Signed-off-by", format!("{name} <{email}>")))
 	} else {
 		None
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fs,
+		process::Command,
+	};
+
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+	use crate::{
+		conf::{
+			Placement,
+			TicketConfig,
+		},
+		test_utils::setup_repo,
+	};
+
+	/// Repository with a single commit and a staged `file.txt`
+	fn setup_staged() -> (TempDir, Repository) {
+		let (dir, repo) = setup_repo(&[]);
+		fs::write(dir.path().join("file.txt"), "content").unwrap();
+		let mut index = repo.index().unwrap();
+		index.add_path(Path::new("file.txt")).unwrap();
+		index.write().unwrap();
+		(dir, repo)
+	}
+
+	#[test]
+	fn test_commit_paths() {
+		let (dir, repo) = setup_staged();
+		fs::write(dir.path().join("Cargo.toml"), "version = '1.0.0'").unwrap();
+		commit_paths(
+			&repo,
+			&[PathBuf::from("Cargo.toml")],
+			"chore(release): v1.0.0",
+		)
+		.unwrap();
+
+		let head = repo.head().unwrap().peel_to_tree().unwrap();
+		assert!(head.get_name("Cargo.toml").is_some());
+		assert!(head.get_name("file.txt").is_none());
+		// the other change is still staged
+		assert_eq!(get_staged_paths(&repo), [PathBuf::from("file.txt")]);
+	}
+
+	#[test]
+	fn test_create_commit() {
+		let (_dir, repo) = setup_staged();
+		let oid = create_commit(&repo, "feat: add file").unwrap();
+		let head = repo.head().unwrap();
+		assert_eq!(head.target(), Some(oid));
+		let commit = head.peel_to_commit().unwrap();
+		assert_eq!(commit.message(), Some("feat: add file\n"));
+		assert_eq!(commit.parent_count(), 1);
+		assert!(commit.tree().unwrap().get_name("file.txt").is_some());
+		assert!(repo.extract_signature(&oid, None).is_err());
+	}
+
+	#[test]
+	fn test_create_commit_ssh_signed() {
+		let (dir, repo) = setup_staged();
+		let key = dir.path().join("id_ed25519");
+		let status = Command::new("ssh-keygen")
+			.args(["-q", "-t", "ed25519", "-N", "", "-f"])
+			.arg(&key)
+			.status()
+			.expect("ssh-keygen is required to test ssh signing");
+		assert!(status.success());
+		{
+			let mut config = repo.config().unwrap();
+			config.set_bool("commit.gpgsign", true).unwrap();
+			config.set_str("gpg.format", "ssh").unwrap();
+			config
+				.set_str("user.signingkey", key.to_str().unwrap())
+				.unwrap();
+		}
+
+		let oid = create_commit(&repo, "feat: add file").unwrap();
+		assert_eq!(repo.head().unwrap().target(), Some(oid));
+		let (signature, signed) = repo.extract_signature(&oid, None).unwrap();
+		let signature = signature.as_str().unwrap();
+		assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+		assert!(signed.as_str().unwrap().ends_with("feat: add file\n"));
+	}
+
+	fn inputs<'a>(breaking_changes: &str, tickets: &[&str]) -> Inputs<'a> {
+		Inputs {
+			change_type: "feat",
+			scope: Some("git".into()),
+			description: "add footer".into(),
+			long_description: "Longer description\n\n".into(),
+			breaking_changes: breaking_changes.into(),
+			tickets: tickets.iter().map(|ticket| ticket.to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn test_gen_commit_msg() {
+		assert_eq!(
+			gen_commit_msg(&inputs("", &[]), true, &TicketConfig::default(), None, None),
+			"feat(git): add footer\n\nLonger description"
+		);
+		assert_eq!(
+			gen_commit_msg(
+				&inputs("", &["ABC-12"]),
+				true,
+				&TicketConfig::default(),
+				None,
+				Some(Footer::new("Signed-off-by", "A <a@b.c>"))
+			),
+			"feat(git): add footer\n\nABC-12\nLonger description\n\nSigned-off-by: A <a@b.c>"
+		);
+	}
+
+	#[test]
+	fn test_gen_commit_msg_breaking() {
+		let message = gen_commit_msg(
+			&inputs("config is gone\n\nuse flags instead\n", &[]),
+			true,
+			&TicketConfig::default(),
+			None,
+			Some(Footer::new("Signed-off-by", "A <a@b.c>")),
+		);
+		assert_eq!(
+			message,
+			"feat(git)!: add footer\n\nLonger description\n\nBREAKING CHANGE: config is gone\n  use \
+			 flags instead\nSigned-off-by: A <a@b.c>"
+		);
+		let commit = ConventionalCommit::parse(&message).unwrap();
+		assert_eq!(
+			commit.footer_values("BREAKING CHANGE").collect::<Vec<_>>(),
+			["config is gone\n  use flags instead"]
+		);
+
+		// looks like a trailer, but belongs to the description
+		let message = gen_commit_msg(
+			&inputs("config is gone\nMigration: run resin init", &[]),
+			true,
+			&TicketConfig::default(),
+			None,
+			Some(Footer::new("Signed-off-by", "A <a@b.c>")),
+		);
+		let commit = ConventionalCommit::parse(&message).unwrap();
+		assert_eq!(
+			commit.footers,
+			[
+				Footer::new(
+					"BREAKING CHANGE",
+					"config is gone\n  Migration: run resin init"
+				),
+				Footer::new("Signed-off-by", "A <a@b.c>"),
+			]
+		);
+		assert_eq!(commit.to_string(), message);
+
+		let message = gen_commit_msg(
+			&inputs("config is gone", &[]),
+			false,
+			&TicketConfig::default(),
+			None,
+			None,
+		);
+		assert!(message.starts_with("feat(git): add footer"));
+		assert!(ConventionalCommit::parse(&message).unwrap().is_breaking());
+	}
+
+	#[test]
+	fn test_gen_commit_msg_tickets() {
+		let trailer = TicketConfig {
+			placement: Some(Placement::Trailer),
+			..Default::default()
+		};
+		assert_eq!(
+			gen_commit_msg(
+				&inputs("gone", &["ABC-12", "ABC-13"]),
+				true,
+				&trailer,
+				None,
+				Some(Footer::new("Signed-off-by", "A <a@b.c>"))
+			),
+			"feat(git)!: add footer\n\nLonger description\n\nBREAKING CHANGE: gone\nRefs: ABC-12\nRefs: \
+			 ABC-13\nSigned-off-by: A <a@b.c>"
+		);
+
+		let header = TicketConfig {
+			placement: Some(Placement::Header),
+			format: Some("[{ticket}]".into()),
+			..Default::default()
+		};
+		assert_eq!(
+			gen_commit_msg(&inputs("", &["ABC-12"]), true, &header, None, None),
+			"feat(git): [ABC-12] add footer\n\nLonger description"
+		);
+	}
+
+	#[test]
+	fn test_branch_name() {
+		let (_dir, repo) = setup_staged();
+		let head = repo.head().unwrap().peel_to_commit().unwrap();
+		repo.branch("feature/ABC-12", &head, false).unwrap();
+		repo.set_head("refs/heads/feature/ABC-12").unwrap();
+		assert_eq!(get_branch_name(&repo).as_deref(), Some("feature/ABC-12"));
+
+		repo.set_head_detached(head.id()).unwrap();
+		assert_eq!(get_branch_name(&repo), None);
+	}
+
+	#[test]
+	fn test_branch_name_unborn() {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+		repo.set_head("refs/heads/ABC-1-first").unwrap();
+		assert_eq!(get_branch_name(&repo).as_deref(), Some("ABC-1-first"));
+	}
+
+	#[test]
+	fn test_branch_name_rebase() {
+		let (_dir, repo) = setup_staged();
+		let head = repo.head().unwrap().peel_to_commit().unwrap();
+		repo.set_head_detached(head.id()).unwrap();
+		let rebase = repo.path().join("rebase-merge");
+		fs::create_dir(&rebase).unwrap();
+		fs::write(rebase.join("head-name"), "refs/heads/ABC-12-rebase\n").unwrap();
+		assert_eq!(get_branch_name(&repo).as_deref(), Some("ABC-12-rebase"));
+	}
+
+	#[test]
+	fn test_branch_name_worktree() {
+		let (dir, repo) = setup_staged();
+		let head = repo.head().unwrap().peel_to_commit().unwrap();
+		let branch = repo.branch("ABC-7-worktree", &head, false).unwrap();
+		let mut options = git2::WorktreeAddOptions::new();
+		options.reference(Some(branch.get()));
+		let path = dir.path().join("worktree");
+		repo.worktree("ABC-7", &path, Some(&options)).unwrap();
+
+		let worktree = Repository::open(&path).unwrap();
+		assert_eq!(
+			get_branch_name(&worktree).as_deref(),
+			Some("ABC-7-worktree")
+		);
+		assert_ne!(get_branch_name(&repo).as_deref(), Some("ABC-7-worktree"));
+	}
+
+	#[test]
+	fn test_initial_commit() {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+		{
+			let mut config = repo.config().unwrap();
+			config.set_str("user.name", "resin").unwrap();
+			config.set_str("user.email", "resin@example.com").unwrap();
+			config.set_bool("commit.gpgsign", false).unwrap();
+		}
+		assert!(is_empty(&repo));
+		fs::write(dir.path().join("file.txt"), "content").unwrap();
+		let mut index = repo.index().unwrap();
+		index.add_path(Path::new("file.txt")).unwrap();
+		index.write().unwrap();
+		assert!(!is_empty(&repo));
+
+		let oid = create_commit(&repo, "chore: initial commit").unwrap();
+		let head = repo.head().unwrap();
+		assert_eq!(head.target(), Some(oid));
+		assert_eq!(head.peel_to_commit().unwrap().parent_count(), 0);
+		assert!(is_empty(&repo));
+	}
+
+	#[test]
+	fn test_identity_from_env() {
+		let (_dir, repo) = setup_staged();
+		let env = |var: &str| match var {
+			"GIT_AUTHOR_NAME" => Some("Jane".into()),
+			"GIT_AUTHOR_DATE" => Some("@1700000000 +0130".into()),
+			_ => None,
+		};
+		let author = get_identity(&repo, "AUTHOR", env).unwrap();
+		assert_eq!(author.name(), Some("Jane"));
+		// not overridden, so it's still the config
+		assert_eq!(author.email(), Some("resin@example.com"));
+		assert_eq!(author.when(), Time::new(1_700_000_000, 90));
+		let committer = get_identity(&repo, "COMMITTER", env).unwrap();
+		assert_eq!(committer.name(), Some("resin"));
+
+		assert_eq!(
+			parse_date("1700000000 -0500").unwrap(),
+			Time::new(1_700_000_000, -300)
+		);
+		assert!(parse_date("yesterday").is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_hooks() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let (_dir, repo) = setup_staged();
+		let hooks = repo.path().join("hooks");
+		fs::create_dir_all(&hooks).unwrap();
+		let write_hook = |name: &str, script: &str| {
+			let path = hooks.join(name);
+			fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+			fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+		};
+		assert!(verify_staged(&repo).is_ok());
+		write_hook("pre-commit", "exit 1");
+		assert!(verify_staged(&repo).is_err());
+
+		write_hook("commit-msg", "sed -i.bak 's/^feat/fix/' \"$1\"");
+		assert_eq!(
+			verify_message(&repo, "feat: add file").unwrap(),
+			"fix: add file\n"
+		);
+		write_hook("commit-msg", "exit 1");
+		assert!(verify_message(&repo, "feat: add file").is_err());
+	}
+
+	#[test]
+	fn test_merge_commit() {
+		let (dir, repo) = setup_staged();
+		create_commit(&repo, "feat: add file").unwrap();
+		// the other side of the merge, not reachable from HEAD
+		let sig = repo.signature().unwrap();
+		let tree = repo.head().unwrap().peel_to_tree().unwrap();
+		let head = repo.head().unwrap().peel_to_commit().unwrap();
+		let other = repo
+			.commit(None, &sig, &sig, "fix: other", &tree, &[&head])
+			.unwrap();
+		fs::write(repo.path().join("MERGE_HEAD"), format!("{other}\n")).unwrap();
+		fs::write(repo.path().join("MERGE_MSG"), "Merge other\n").unwrap();
+		fs::write(dir.path().join("file.txt"), "merged").unwrap();
+		repo
+			.index()
+			.unwrap()
+			.add_path(Path::new("file.txt"))
+			.unwrap();
+		assert_eq!(repo.state(), RepositoryState::Merge);
+
+		let oid = create_commit(&repo, "fix: merge other").unwrap();
+		let commit = repo.find_commit(oid).unwrap();
+		assert_eq!(commit.parent_ids().collect::<Vec<_>>(), [head.id(), other]);
+		assert_eq!(repo.state(), RepositoryState::Clean);
+		assert!(!repo.path().join("MERGE_HEAD").exists());
+	}
+
+	#[test]
+	fn test_stage_selection() {
+		let (dir, repo) = setup_staged();
+		create_commit(&repo, "feat: add file").unwrap();
+		fs::write(dir.path().join("file.txt"), "changed").unwrap();
+		fs::write(dir.path().join("untracked.txt"), "new").unwrap();
+		fs::write(dir.path().join("other.txt"), "new").unwrap();
+		let files = get_statuses(&repo).unwrap();
+		assert_eq!(
+			files.iter().map(FileStatus::label).collect::<Vec<_>>(),
+			[" M file.txt", "?? other.txt", "?? untracked.txt"]
+		);
+
+		stage_selection(&repo, &files, &[0, 2]).unwrap();
+		let files = get_statuses(&repo).unwrap();
+		assert_eq!(
+			files.iter().map(FileStatus::label).collect::<Vec<_>>(),
+			["M  file.txt", "?? other.txt", "A  untracked.txt"]
+		);
+	}
+
+	#[test]
+	fn test_stage_deletion() {
+		let (dir, repo) = setup_staged();
+		create_commit(&repo, "feat: add file").unwrap();
+		fs::remove_file(dir.path().join("file.txt")).unwrap();
+		let files = get_statuses(&repo).unwrap();
+		assert_eq!(files[0].label(), " D file.txt");
+		stage_selection(&repo, &files, &[0]).unwrap();
+		assert_eq!(get_statuses(&repo).unwrap()[0].label(), "D  file.txt");
+	}
+
+	#[test]
+	fn test_add_all() {
+		let (dir, repo) = setup_staged();
+		create_commit(&repo, "feat: add file").unwrap();
+		fs::remove_file(dir.path().join("file.txt")).unwrap();
+		fs::write(dir.path().join("new.txt"), "new").unwrap();
+
+		update_all(&mut repo.index().unwrap());
+		let labels = |repo: &Repository| {
+			get_statuses(repo)
+				.unwrap()
+				.iter()
+				.map(FileStatus::label)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(labels(&repo), ["D  file.txt", "?? new.txt"]);
+
+		add_all(&mut repo.index().unwrap());
+		assert_eq!(labels(&repo), ["D  file.txt", "A  new.txt"]);
+	}
+
+	#[test]
+	fn test_diffstat_renames() {
+		let (dir, repo) = setup_staged();
+		fs::write(dir.path().join("file.txt"), "some content\n".repeat(10)).unwrap();
+		let mut index = repo.index().unwrap();
+		index.add_path(Path::new("file.txt")).unwrap();
+		index.write().unwrap();
+		create_commit(&repo, "feat: add file").unwrap();
+
+		fs::rename(dir.path().join("file.txt"), dir.path().join("moved.txt")).unwrap();
+		add_all(&mut repo.index().unwrap());
+		let diffstat = get_diffstat(&repo).unwrap();
+		assert!(diffstat.contains("file.txt => moved.txt"), "{diffstat}");
+		assert!(diffstat.contains("1 file changed"), "{diffstat}");
+	}
+
+	#[test]
+	fn test_gen_commit_msg_emoji() {
+		let prefix = Some((conf::EmojiMode::Prefix, String::from("✨")));
+		assert_eq!(
+			gen_commit_msg(
+				&inputs("", &[]),
+				true,
+				&TicketConfig::default(),
+				prefix,
+				None
+			),
+			"✨ feat(git): add footer\n\nLonger description"
+		);
+		let replace = Some((conf::EmojiMode::Replace, String::from(":sparkles:")));
+		assert_eq!(
+			gen_commit_msg(
+				&inputs("gone", &[]),
+				true,
+				&TicketConfig::default(),
+				replace,
+				None
+			),
+			":sparkles:(git)!: add footer\n\nLonger description\n\nBREAKING CHANGE: gone"
+		);
+	}
+}
@@ -0,0 +1,288 @@
+use std::{
+	env::current_exe,
+	fs,
+	path::{
+		Path,
+		PathBuf,
+	},
+	process::Command,
+};
+
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use clap::{
+	ArgMatches,
+	ValueEnum,
+};
+use git2::Repository;
+use strum::Display;
+
+use crate::{
+	conf::Config,
+	git::{
+		gen_message,
+		get_repo,
+	},
+	inputs::get_inputs,
+	lint,
+	utils::{
+		output_info,
+		output_success,
+	},
+};
+
+/// Marks hooks written by resin so we never touch anybody else's hooks
+static MARKER: &str = "# installed by resin";
+
+#[derive(Clone, Copy, Debug, Display, PartialEq, ValueEnum)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Hook {
+	CommitMsg,
+	PrepareCommitMsg,
+}
+
+impl Hook {
+	fn script(&self, resin: &Path) -> String {
+		let resin = resin.display();
+		format!("#!/bin/sh\n{MARKER}\nexec \"{resin}\" hook {self} \"$@\"\n")
+	}
+}
+
+/// Entry point of `resin hook`
+///
+/// Returns whether the hook succeeded
+pub fn run(config: &Config, root_args: &ArgMatches, args: &ArgMatches) -> Result<bool> {
+	match args.subcommand() {
+		Some(("install", args)) => {
+			let repo = Repository::open_from_env()?;
+			let hook = *args.get_one::<Hook>("hook").unwrap();
+			let path = install(&repo, hook, &current_exe()?, args.get_flag("force"))?;
+			output_success(&format!("Installed {hook} hook to {}", path.display()));
+			Ok(true)
+		}
+		Some(("uninstall", args)) => {
+			let repo = Repository::open_from_env()?;
+			let hook = *args.get_one::<Hook>("hook").unwrap();
+			let path = uninstall(&repo, hook)?;
+			output_success(&format!("Removed {hook} hook from {}", path.display()));
+			Ok(true)
+		}
+		Some(("commit-msg", args)) => lint::run_file(config, args.get_one::<PathBuf>("file").unwrap()),
+		Some(("prepare-commit-msg", args)) => {
+			if has_message(args.get_one::<String>("source").map(String::as_str)) {
+				return Ok(true);
+			}
+			prepare_commit_msg(config, root_args, args.get_one::<PathBuf>("file").unwrap())?;
+			Ok(true)
+		}
+		_ => unreachable!("clap requires a subcommand"),
+	}
+}
+
+/// Whether git already has a message (`-m`, `--amend`, merges, ...),
+/// `commit.template` still deserves the prompts
+fn has_message(source: Option<&str>) -> bool {
+	matches!(source, Some("message" | "merge" | "squash" | "commit"))
+}
+
+/// Runs the prompts and writes the message into the file git opens in the editor
+fn prepare_commit_msg(config: &Config, args: &ArgMatches, path: &Path) -> Result<()> {
+	if let Err(e) = reopen_tty() {
+		// let git continue with its own editor
+		output_info(&format!("Skipping resin prompts: {e:#}"));
+		return Ok(());
+	}
+	let inputs = get_inputs(config, args)?;
+	let repo = get_repo();
+	let message = gen_message(&repo, config, &inputs);
+	// keep git's comments so the editor still shows the status
+	let template = fs::read_to_string(path).unwrap_or_default();
+	fs::write(path, format!("{message}\n{template}"))
+		.with_context(|| format!("Failed to write {}", path.display()))?;
+	Ok(())
+}
+
+/// git runs hooks with stdin redirected, but the prompts need a terminal
+#[cfg(unix)]
+fn reopen_tty() -> Result<()> {
+	use std::os::fd::AsRawFd;
+
+	let tty = fs::File::open("/dev/tty").context("Failed to open /dev/tty")?;
+	// SAFETY: both file descriptors are valid for the duration of the call
+	if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDIN_FILENO) } == -1 {
+		return Err(std::io::Error::last_os_error()).context("Failed to redirect stdin to /dev/tty");
+	}
+	Ok(())
+}
+
+#[cfg(not(unix))]
+fn reopen_tty() -> Result<()> {
+	bail!("reopening the terminal is only supported on unix")
+}
+
+/// Runs one of git's own hooks like `pre-commit` if it is installed,
+/// `git commit` did that for us before resin created commits itself
+pub fn run_git_hook(repo: &Repository, name: &str, args: &[&Path]) -> Result<()> {
+	let path = hooks_dir(repo)?.join(name);
+	if !is_executable(&path) {
+		return Ok(());
+	}
+	let status = Command::new(&path)
+		.args(args)
+		// git runs hooks from the root of the working tree
+		.current_dir(repo.workdir().unwrap_or(repo.path()))
+		.status()
+		.with_context(|| format!("Failed to run the {name} hook"))?;
+	if !status.success() {
+		bail!("The {name} hook failed, use --no-verify to skip it");
+	}
+	Ok(())
+}
+
+/// git ignores hooks that aren't executable
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+
+	fs::metadata(path)
+		.is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+	path.is_file()
+}
+
+/// Respects `core.hooksPath` like git does
+fn hooks_dir(repo: &Repository) -> Result<PathBuf> {
+	let config = repo.config()?;
+	match config.get_path("core.hooksPath") {
+		// relative paths are relative to the directory the hooks are run in
+		Ok(path) if path.is_relative() => Ok(repo.workdir().unwrap_or(repo.path()).join(path)),
+		Ok(path) => Ok(path),
+		Err(_) => Ok(common_dir(repo)?.join("hooks")),
+	}
+}
+
+/// Worktrees share the hooks of the main repository
+fn common_dir(repo: &Repository) -> Result<PathBuf> {
+	if !repo.is_worktree() {
+		return Ok(repo.path().into());
+	}
+	let common = fs::read_to_string(repo.path().join("commondir"))?;
+	Ok(repo.path().join(common.trim()))
+}
+
+fn is_ours(path: &Path) -> bool {
+	fs::read_to_string(path).is_ok_and(|content| content.contains(MARKER))
+}
+
+fn install(repo: &Repository, hook: Hook, resin: &Path, force: bool) -> Result<PathBuf> {
+	let dir = hooks_dir(repo)?;
+	let path = dir.join(hook.to_string());
+	if path.exists() && !is_ours(&path) && !force {
+		bail!(
+			"{} already exists and wasn't installed by resin, use --force to overwrite it",
+			path.display()
+		);
+	}
+	fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+	fs::write(&path, hook.script(resin))
+		.with_context(|| format!("Failed to write {}", path.display()))?;
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+	}
+	Ok(path)
+}
+
+fn uninstall(repo: &Repository, hook: Hook) -> Result<PathBuf> {
+	let path = hooks_dir(repo)?.join(hook.to_string());
+	if !path.exists() {
+		bail!("There is no {hook} hook installed");
+	}
+	if !is_ours(&path) {
+		bail!("{} wasn't installed by resin", path.display());
+	}
+	fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+	Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+
+	fn setup() -> (TempDir, Repository) {
+		let dir = TempDir::new().unwrap();
+		let repo = Repository::init(dir.path()).unwrap();
+		(dir, repo)
+	}
+
+	#[test]
+	fn test_install() {
+		let (_dir, repo) = setup();
+		let path = install(&repo, Hook::CommitMsg, Path::new("/bin/resin"), false).unwrap();
+		assert_eq!(path, repo.path().join("hooks").join("commit-msg"));
+		let script = fs::read_to_string(&path).unwrap();
+		assert!(script.contains("exec \"/bin/resin\" hook commit-msg \"$@\""));
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			assert_eq!(
+				fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+				0o755
+			);
+		}
+		// installing again just updates our own hook
+		install(&repo, Hook::CommitMsg, Path::new("/usr/bin/resin"), false).unwrap();
+	}
+
+	#[test]
+	fn test_hooks_path() {
+		let (dir, repo) = setup();
+		repo
+			.config()
+			.unwrap()
+			.set_str("core.hooksPath", ".githooks")
+			.unwrap();
+		let path = install(&repo, Hook::CommitMsg, Path::new("resin"), false).unwrap();
+		assert_eq!(
+			path.canonicalize().unwrap(),
+			dir
+				.path()
+				.join(".githooks/commit-msg")
+				.canonicalize()
+				.unwrap()
+		);
+	}
+
+	#[test]
+	fn test_has_message() {
+		assert!(!has_message(None));
+		assert!(!has_message(Some("template")));
+		for source in ["message", "merge", "squash", "commit"] {
+			assert!(has_message(Some(source)));
+		}
+	}
+
+	#[test]
+	fn test_foreign_hook() {
+		let (_dir, repo) = setup();
+		let path = hooks_dir(&repo).unwrap().join("commit-msg");
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(&path, "#!/bin/sh\nexit 0\n").unwrap();
+
+		assert!(install(&repo, Hook::CommitMsg, Path::new("resin"), false).is_err());
+		assert!(uninstall(&repo, Hook::CommitMsg).is_err());
+		install(&repo, Hook::CommitMsg, Path::new("resin"), true).unwrap();
+		uninstall(&repo, Hook::CommitMsg).unwrap();
+		assert!(!path.exists());
+	}
+}
@@ -1,4 +1,8 @@
 use std::{
+	io::{
+		stdin,
+		IsTerminal,
+	},
 	path::{
 		Path,
 		PathBuf,
@@ -7,28 +11,42 @@ use std::{
 };
 
 use anyhow::{
+	bail,
 	Context,
 	Result,
 };
+use clap::ArgMatches;
 use dialoguer::{
 	theme::ColorfulTheme,
 	BasicHistory,
 	Confirm,
 	FuzzySelect,
 	Input,
+	MultiSelect,
+	Select,
 };
 use git2::Repository;
+use strum::Display;
 use sys_locale::get_locale;
 
 use crate::{
-	conf,
+	conf::{
+		self,
+		Placement,
+	},
 	git::{
 		get_branch_name,
 		get_repo,
 	},
+	review::ReviewChoice,
+	stage::HunkChoice,
+	suggest::{
+		suggest_scope,
+		suggest_type,
+	},
 	utils::{
 		fail,
-		parse_jira,
+		output_info,
 	},
 };
 
@@ -38,79 +56,340 @@ pub struct Inputs<'a> {
 	pub description: String,
 	pub long_description: String,
 	pub breaking_changes: String,
-	pub ticket: String,
+	pub tickets: Vec<String>,
+}
+
+/// A prompt that can be answered again from the review
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+pub enum Field {
+	Type,
+	Scope,
+	Description,
+	#[strum(serialize = "Longer description")]
+	Body,
+	#[strum(serialize = "Breaking change")]
+	BreakingChanges,
+	Tickets,
+}
+
+impl Field {
+	pub const ALL: [Self; 6] = [
+		Self::Type,
+		Self::Scope,
+		Self::Description,
+		Self::Body,
+		Self::BreakingChanges,
+		Self::Tickets,
+	];
 }
 
+static CUSTOM_SCOPE: &str = "[custom scope]";
+
+static EDITOR_COMMAND: &str = ":e";
+
 static THEME: OnceLock<ColorfulTheme> = OnceLock::new();
 
 fn get_theme() -> &'static ColorfulTheme {
 	THEME.get_or_init(ColorfulTheme::default)
 }
 
-pub fn get_inputs(config: &conf::Config) -> Result<Inputs<'_>> {
+pub fn get_inputs<'a>(config: &'a conf::Config, args: &'a ArgMatches) -> Result<Inputs<'a>> {
 	let theme = get_theme();
 	let repo = get_repo();
-	// before doing anything check index
-	super::git::check_emptiness(&repo);
-
-	let type_selection = ask_for_change(theme, config.get_type_items())?;
-	// I think panicing here is ok
-	let type_: &str = config.get_type_items().get(type_selection).unwrap();
-	let scope = ask_for_scope(theme)?;
-	let scope = if scope {
-		let scope = get_for_scope(theme)?;
-		let len = scope.len();
-		(Some(scope), len)
-	} else {
-		(None, 0)
-	};
-	let description: String = ask_for_desc(theme, calc_max_len(&type_.len(), &scope.1))?;
-	let long_description: bool = ask_for_long_desc(theme)?;
+	// skip every optional prompt that wasn't passed as a flag
+	let yes = args.get_flag("yes");
 
-	let long_description = if long_description {
-		get_long_desc()?
+	let type_: &str = match args.get_one::<String>("type") {
+		Some(type_) => validate_type(config, type_)?,
+		None => {
+			require_tty("--type")?;
+			let suggestion = suggest_type(config, &repo);
+			let type_selection = ask_for_change(theme, config, suggestion)?;
+			// I think panicing here is ok
+			config.get_type_items().get(type_selection).unwrap()
+		}
+	};
+	let scope = match args.get_one::<String>("scope") {
+		Some(scope) => Some(validate_scope(config, scope)?),
+		None if yes => None,
+		None => {
+			require_tty("--scope")?;
+			let suggestion = suggest_scope(config, &repo);
+			if ask_for_scope(theme, suggestion.is_some())? {
+				Some(get_for_scope(theme, config, suggestion.as_deref())?)
+			} else {
+				None
+			}
+		}
+	};
+	// tickets in the header take away from the description
+	let header_tickets = if config.get_ticket().get_placement() == Placement::Header {
+		Some(get_tickets(theme, config, args, yes, &repo)?)
 	} else {
-		String::new()
+		None
+	};
+	let prefix_len = header_tickets
+		.as_ref()
+		.and_then(|tickets| config.get_ticket().header_prefix(tickets))
+		.map_or(0, |prefix| prefix.len() + 1);
+	let max_len = calc_max_len(
+		&config.type_header_len(type_),
+		&scope.as_ref().map_or(0, String::len),
+	)
+	.saturating_sub(prefix_len);
+	let description: String = match args.get_one::<String>("message") {
+		Some(description) => {
+			if description.len() > max_len {
+				output_info(&format!(
+					"The description is longer than the recommended {max_len} chars"
+				));
+			}
+			description.clone()
+		}
+		None => {
+			require_tty("--message")?;
+			ask_for_desc(theme, max_len)?
+		}
+	};
+	let long_description = match args.get_one::<String>("body") {
+		Some(body) => body.clone(),
+		None if yes => String::new(),
+		None => {
+			require_tty("--body")?;
+			if ask_for_long_desc(theme)? {
+				get_long_desc()?
+			} else {
+				String::new()
+			}
+		}
+	};
+	let breaking_changes: String = match args.get_one::<String>("breaking") {
+		Some(breaking_changes) => breaking_changes.clone(),
+		None if yes => String::new(),
+		None => {
+			require_tty("--breaking")?;
+			ask_for_breaking_changes(theme)?
+		}
+	};
+	let tickets = match header_tickets {
+		Some(tickets) => tickets,
+		None => get_tickets(theme, config, args, yes, &repo)?,
 	};
-
-	let breaking_changes: String = ask_for_breaking_changes(theme)?;
-	let ticket: String = ask_for_ticket(theme, &repo)?;
 	Ok(Inputs {
 		change_type: type_,
-		scope: scope.0,
+		scope,
 		description,
 		long_description,
 		breaking_changes,
-		ticket,
+		tickets,
 	})
 }
 
-fn ask_for_change(theme: &ColorfulTheme, items: &[String]) -> Result<usize, anyhow::Error> {
+/// Asks for a single field again, flags are ignored this time
+pub fn ask_again<'a>(
+	config: &'a conf::Config,
+	inputs: &mut Inputs<'a>,
+	field: Field,
+) -> Result<()> {
+	let theme = get_theme();
+	match field {
+		Field::Type => {
+			let suggestion = suggest_type(config, &get_repo());
+			let selection = ask_for_change(theme, config, suggestion)?;
+			inputs.change_type = config.get_type_items().get(selection).unwrap();
+		}
+		Field::Scope => {
+			let suggestion = suggest_scope(config, &get_repo());
+			inputs.scope = if ask_for_scope(theme, suggestion.is_some())? {
+				Some(get_for_scope(theme, config, suggestion.as_deref())?)
+			} else {
+				None
+			};
+		}
+		Field::Description => {
+			let prefix_len = config
+				.get_ticket()
+				.header_prefix(&inputs.tickets)
+				.map_or(0, |prefix| prefix.len() + 1);
+			let max_len = calc_max_len(
+				&config.type_header_len(inputs.change_type),
+				&inputs.scope.as_ref().map_or(0, String::len),
+			)
+			.saturating_sub(prefix_len);
+			inputs.description = ask_for_desc(theme, max_len)?;
+		}
+		Field::Body => inputs.long_description = get_long_desc()?,
+		Field::BreakingChanges => inputs.breaking_changes = ask_for_breaking_changes(theme)?,
+		Field::Tickets => inputs.tickets = ask_for_tickets(theme, config, &get_repo())?,
+	}
+	Ok(())
+}
+
+fn get_tickets(
+	theme: &ColorfulTheme,
+	config: &conf::Config,
+	args: &ArgMatches,
+	yes: bool,
+	repo: &Repository,
+) -> Result<Vec<String>> {
+	match args.get_many::<String>("ticket") {
+		Some(tickets) => Ok(tickets.cloned().collect()),
+		None if yes => Ok(detect_ticket(config, repo).into_iter().collect()),
+		None => {
+			require_tty("--ticket")?;
+			ask_for_tickets(theme, config, repo)
+		}
+	}
+}
+
+/// Prompts need a terminal, so without one every missing field is an error
+fn require_tty(flag: &str) -> Result<()> {
+	if stdin().is_terminal() {
+		Ok(())
+	} else {
+		bail!("stdin is not a terminal, pass {flag} (or --yes to skip optional fields)")
+	}
+}
+
+fn validate_type<'a>(config: &'a conf::Config, type_: &str) -> Result<&'a str> {
+	config
+		.get_type_items()
+		.iter()
+		.find(|item| *item == type_)
+		.map(String::as_str)
+		.with_context(|| {
+			format!(
+				"Unknown type `{type_}`, expected one of: {}",
+				config.get_type_items().join(", ")
+			)
+		})
+}
+
+fn validate_scope(config: &conf::Config, scope: &str) -> Result<String> {
+	if config.is_scope_strict() && !config.get_scope_items().iter().any(|item| item == scope) {
+		bail!(
+			"Unknown scope `{scope}`, expected one of: {}",
+			config.get_scope_items().join(", ")
+		);
+	}
+	Ok(scope.into())
+}
+
+fn ask_for_change(
+	theme: &ColorfulTheme,
+	config: &conf::Config,
+	suggestion: Option<&str>,
+) -> Result<usize, anyhow::Error> {
+	let items = config.get_type_items();
+	// only a default, the suggestion can be wrong
+	let default = suggestion
+		.and_then(|suggestion| items.iter().position(|item| item == suggestion))
+		.unwrap_or_default();
+	let labels = item_labels(items.iter().map(String::as_str), |item| {
+		(
+			config.get_type_emoji(item),
+			config.get_type_description(item),
+		)
+	});
 	FuzzySelect::with_theme(theme)
 		.with_prompt("Type")
-		.default(0)
-		.items(items)
+		.default(default)
+		.items(&labels)
 		.interact()
 		.context("Failed to present change type selection to user")
 }
 
-fn ask_for_scope(theme: &ColorfulTheme) -> Result<bool, anyhow::Error> {
+/// `⚡ perf      A code change that improves performance`
+fn item_labels<'a>(
+	items: impl Iterator<Item = &'a str> + Clone,
+	describe: impl Fn(&str) -> (Option<&'a str>, Option<&'a str>),
+) -> Vec<String> {
+	let width = items
+		.clone()
+		.map(|item| item.chars().count())
+		.max()
+		.unwrap_or_default();
+	let any_emoji = items.clone().any(|item| describe(item).0.is_some());
+	items
+		.map(|item| {
+			let (emoji, description) = describe(item);
+			let mut label = match emoji {
+				Some(emoji) => format!("{emoji} "),
+				// emojis are two columns wide
+				None if any_emoji => "   ".into(),
+				None => String::new(),
+			};
+			match description {
+				Some(description) => label.push_str(&format!("{item:<width$}  {description}")),
+				None => label.push_str(item),
+			}
+			label
+		})
+		.collect()
+}
+
+fn ask_for_scope(theme: &ColorfulTheme, suggested: bool) -> Result<bool, anyhow::Error> {
 	Confirm::with_theme(theme)
-		.default(false)
+		.default(suggested)
 		.with_prompt("Scope (optional)")
 		.wait_for_newline(true)
 		.interact()
 		.context("Failed to ask for longer description")
 }
 
-fn get_for_scope(theme: &ColorfulTheme) -> Result<String, anyhow::Error> {
-	Input::with_theme(theme)
+fn get_for_scope(
+	theme: &ColorfulTheme,
+	config: &conf::Config,
+	suggestion: Option<&str>,
+) -> Result<String, anyhow::Error> {
+	let items = config.get_scope_items();
+	let strict = config.is_scope_strict();
+	if items.is_empty() && suggestion.is_none() {
+		if strict {
+			bail!("scopes.strict is set but no scopes are configured");
+		}
+		return get_custom_scope(theme);
+	}
+	let mut options: Vec<&str> = items.iter().map(String::as_str).collect();
+	// the suggestion might not be configured, e.g. a directory name
+	if let Some(suggestion) = suggestion.filter(|suggestion| !options.contains(suggestion)) {
+		options.insert(0, suggestion);
+	}
+	let scopes = options.len();
+	// in strict mode only the configured scopes are allowed
+	if !strict {
+		options.push(CUSTOM_SCOPE);
+	}
+	let default = suggestion
+		.and_then(|suggestion| options.iter().position(|option| *option == suggestion))
+		.unwrap_or_default();
+	let labels = item_labels(options.iter().copied(), |item| {
+		(
+			config.get_scope_emoji(item),
+			config.get_scope_description(item),
+		)
+	});
+	let selection = FuzzySelect::with_theme(theme)
 		.with_prompt("Scope")
+		.default(default)
+		.items(&labels)
+		.interact()
+		.context("Failed to present scope selection to user")?;
+	if selection < scopes {
+		Ok(options[selection].into())
+	} else {
+		get_custom_scope(theme)
+	}
+}
+
+fn get_custom_scope(theme: &ColorfulTheme) -> Result<String, anyhow::Error> {
+	Input::with_theme(theme)
+		.with_prompt("Custom scope")
 		.interact_text()
-		.context("Failed to present scope selection to user")
+		.context("Failed to ask for custom scope")
 }
 
-fn calc_max_len(change_type_len: &usize, scope_len: &usize) -> usize {
+pub fn calc_max_len(change_type_len: &usize, scope_len: &usize) -> usize {
 	// type + `: `
 	let change_type_len = change_type_len + 2;
 	let scope_len = if scope_len == &0 {
@@ -120,7 +399,9 @@ fn calc_max_len(change_type_len: &usize, scope_len: &usize) -> usize {
 		scope_len + 2
 	};
 	// header is only supposed to be 50 chars long
-	50 - change_type_len - scope_len
+	50usize
+		.saturating_sub(change_type_len)
+		.saturating_sub(scope_len)
 }
 
 fn ask_for_desc(theme: &ColorfulTheme, max_input_length: usize) -> Result<String, anyhow::Error> {
@@ -155,12 +436,25 @@ fn get_long_desc() -> Result<String, anyhow::Error> {
 		"de-DE" => include_str!("templates/de.template"),
 		_ => include_str!("templates/en.template"),
 	};
-	let long_description = edit::edit(template)?;
-	let long_description = long_description
+	edit_template(template)
+}
+
+fn get_breaking_desc() -> Result<String, anyhow::Error> {
+	let locale = get_locale().unwrap_or_else(|| String::from("en-US"));
+	let template = match locale.as_str() {
+		"de-DE" => include_str!("templates/breaking.de.template"),
+		_ => include_str!("templates/breaking.en.template"),
+	};
+	edit_template(template)
+}
+
+fn edit_template(template: &str) -> Result<String, anyhow::Error> {
+	let text = edit::edit(template)?;
+	let text = text
 		.lines()
 		.filter(move |line| !line.starts_with('#'))
 		.fold(String::new(), |s, l| s + l + "\n");
-	Ok(long_description)
+	Ok(text)
 }
 
 fn ask_for_long_desc(theme: &ColorfulTheme) -> Result<bool, anyhow::Error> {
@@ -173,26 +467,47 @@ fn ask_for_long_desc(theme: &ColorfulTheme) -> Result<bool, anyhow::Error> {
 }
 
 fn ask_for_breaking_changes(theme: &ColorfulTheme) -> Result<String, anyhow::Error> {
-	Input::with_theme(theme)
+	let breaking_changes: String = Input::with_theme(theme)
 		.allow_empty(true)
-		.with_prompt("Breaking change (optional)")
+		.with_prompt(format!(
+			"Breaking change (optional, {EDITOR_COMMAND} opens the editor)"
+		))
 		.interact_text()
-		.context("Failed to ask for breaking changes")
+		.context("Failed to ask for breaking changes")?;
+	// long descriptions are easier to write in a real editor
+	if breaking_changes.trim() == EDITOR_COMMAND {
+		get_breaking_desc()
+	} else {
+		Ok(breaking_changes)
+	}
 }
 
-fn ask_for_ticket(theme: &ColorfulTheme, repo: &Repository) -> Result<String, anyhow::Error> {
+fn detect_ticket(config: &conf::Config, repo: &Repository) -> Option<String> {
 	// there has to be a better solution...
-	let init = if let Some(name) = get_branch_name(repo) {
-		parse_jira(&name).unwrap_or_default()
-	} else {
-		String::new()
-	};
-	Input::with_theme(theme)
+	get_branch_name(repo).and_then(|name| config.get_ticket().detect(&name))
+}
+
+fn ask_for_tickets(
+	theme: &ColorfulTheme,
+	config: &conf::Config,
+	repo: &Repository,
+) -> Result<Vec<String>, anyhow::Error> {
+	let tickets: String = Input::with_theme(theme)
 		.allow_empty(true)
-		.with_initial_text(init)
-		.with_prompt("Ticket (optional)")
+		.with_initial_text(detect_ticket(config, repo).unwrap_or_default())
+		.with_prompt("Tickets (optional, comma separated)")
 		.interact_text()
-		.context("Failed to ask for ticket")
+		.context("Failed to ask for tickets")?;
+	Ok(split_tickets(&tickets))
+}
+
+fn split_tickets(tickets: &str) -> Vec<String> {
+	tickets
+		.split([',', ' '])
+		.map(str::trim)
+		.filter(|ticket| !ticket.is_empty())
+		.map(Into::into)
+		.collect()
 }
 
 pub fn ask_for_path() -> bool {
@@ -216,3 +531,98 @@ pub fn prompt_for_path() -> PathBuf {
 	}
 	Path::new(path.unwrap().as_str()).to_path_buf()
 }
+
+pub fn ask_for_upstream(branch: &str, remote: &str) -> bool {
+	let upstream = Confirm::with_theme(get_theme())
+		.default(true)
+		.with_prompt(format!(
+			"Branch {branch} has no upstream, push to {remote}?"
+		))
+		.wait_for_newline(true)
+		.interact();
+	if upstream.is_err() {
+		fail("ask for upstream");
+	}
+	upstream.unwrap()
+}
+
+pub fn select_remote(remotes: &[&str]) -> usize {
+	let remote = FuzzySelect::with_theme(get_theme())
+		.with_prompt("Remote")
+		.default(0)
+		.items(remotes)
+		.interact();
+	if remote.is_err() {
+		fail("select remote");
+	}
+	remote.unwrap()
+}
+
+/// Returns the indices of the checked files
+pub fn select_files(files: &[String]) -> Vec<usize> {
+	let selection = MultiSelect::with_theme(get_theme())
+		.with_prompt("Files to stage (space to toggle)")
+		.items(files)
+		.interact();
+	if selection.is_err() {
+		fail("select files");
+	}
+	selection.unwrap()
+}
+
+pub fn ask_for_hunk(position: &str) -> HunkChoice {
+	let choice = Select::with_theme(get_theme())
+		.with_prompt(format!("Stage this hunk {position}?"))
+		.default(0)
+		.items(&HunkChoice::ALL)
+		.interact();
+	if choice.is_err() {
+		fail("ask for hunk");
+	}
+	HunkChoice::ALL[choice.unwrap()]
+}
+
+pub fn ask_for_review() -> ReviewChoice {
+	let choice = Select::with_theme(get_theme())
+		.with_prompt("Commit?")
+		.default(0)
+		.items(&ReviewChoice::ALL)
+		.interact();
+	if choice.is_err() {
+		fail("ask for review");
+	}
+	ReviewChoice::ALL[choice.unwrap()]
+}
+
+pub fn select_field() -> Field {
+	let field = Select::with_theme(get_theme())
+		.with_prompt("Go back to")
+		.default(0)
+		.items(&Field::ALL)
+		.interact();
+	if field.is_err() {
+		fail("select field");
+	}
+	Field::ALL[field.unwrap()]
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	#[test]
+	fn test_item_labels() {
+		let labels = item_labels(["perf", "fix", "wip"].into_iter(), |item| match item {
+			"perf" => (Some("⚡"), Some("Faster")),
+			"fix" => (None, Some("A bug fix")),
+			_ => (None, None),
+		});
+		assert_eq!(labels, ["⚡ perf  Faster", "   fix   A bug fix", "   wip"]);
+		assert_eq!(
+			item_labels(["a", "b"].into_iter(), |_| (None, None)),
+			["a", "b"]
+		);
+	}
+}
@@ -0,0 +1,13 @@
+//! Parsing and formatting of [conventional commits](https://www.conventionalcommits.org/)
+//!
+//! This is the grammar the resin CLI produces and lints,
+//! exposed so other tools don't have to re-implement it.
+mod conventional;
+pub mod emoji;
+
+pub use conventional::{
+	ConventionalCommit,
+	Footer,
+	ParseError,
+	Separator,
+};
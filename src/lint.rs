@@ -0,0 +1,342 @@
+use std::{
+	fmt::Display,
+	fs::read_to_string,
+	io::{
+		read_to_string as read_all,
+		stdin,
+	},
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+use anyhow::{
+	Context,
+	Result,
+};
+use clap::ArgMatches;
+use git2::Repository;
+use resin::{
+	emoji,
+	ConventionalCommit,
+	ParseError,
+};
+
+use crate::{
+	conf::Config,
+	inputs::calc_max_len,
+	utils::{
+		output_failure,
+		output_info,
+		output_success,
+	},
+};
+
+/// git appends everything below this line for `commit --verbose`
+static SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+	/// 1-based line inside the commit message
+	line: usize,
+	message: String,
+	/// Warnings are reported but don't make the message invalid
+	warning: bool,
+}
+
+impl Diagnostic {
+	fn new(line: usize, message: impl Into<String>) -> Self {
+		Self {
+			line,
+			message: message.into(),
+			warning: false,
+		}
+	}
+
+	fn warning(line: usize, message: impl Into<String>) -> Self {
+		Self {
+			warning: true,
+			..Self::new(line, message)
+		}
+	}
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.warning {
+			write!(f, "{}: warning: {}", self.line, self.message)
+		} else {
+			write!(f, "{}: {}", self.line, self.message)
+		}
+	}
+}
+
+/// Entry point of `resin lint`
+///
+/// Returns whether every message is valid
+pub fn run(config: &Config, args: &ArgMatches) -> Result<bool> {
+	// stored commits were already cleaned up by git, a `#` line belongs to the message
+	let (messages, strip_comments) = if let Some(range) = args.get_one::<String>("range") {
+		(read_range(range)?, false)
+	} else if let Some(path) = args.get_one::<PathBuf>("file") {
+		(vec![read_file(path)?], true)
+	} else {
+		(vec![("stdin".into(), read_all(stdin())?)], true)
+	};
+	Ok(report(config, messages, strip_comments))
+}
+
+/// Lint a single message file, e.g. the one passed to the `commit-msg` hook
+pub fn run_file(config: &Config, path: &Path) -> Result<bool> {
+	Ok(report(config, vec![read_file(path)?], true))
+}
+
+fn read_file(path: &Path) -> Result<(String, String)> {
+	let message = if path.as_os_str() == "-" {
+		read_all(stdin())?
+	} else {
+		read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+	};
+	Ok((path.display().to_string(), message))
+}
+
+fn report(config: &Config, messages: Vec<(String, String)>, strip_comments: bool) -> bool {
+	let mut valid = true;
+	for (source, message) in messages {
+		for diagnostic in check(config, clean(&message, strip_comments)) {
+			if diagnostic.warning {
+				output_info(&format!("{source}:{diagnostic}"));
+			} else {
+				valid = false;
+				output_failure(&format!("{source}:{diagnostic}"));
+			}
+		}
+	}
+	if valid {
+		output_success("Commit message is valid");
+	}
+	valid
+}
+
+/// Messages of every non-merge commit in `range` (`from..to` or a single revision)
+fn read_range(range: &str) -> Result<Vec<(String, String)>> {
+	let repo = Repository::open_from_env()?;
+	let mut revwalk = repo.revwalk()?;
+	if range.contains("..") {
+		revwalk.push_range(range)?;
+	} else {
+		let commit = repo.revparse_single(range)?.peel_to_commit()?;
+		return Ok(vec![(
+			short_id(&commit),
+			commit.message().unwrap_or_default().into(),
+		)]);
+	}
+	let mut messages = Vec::new();
+	for oid in revwalk {
+		let commit = repo.find_commit(oid?)?;
+		// merge commits are generated by git
+		if commit.parent_count() > 1 {
+			continue;
+		}
+		messages.push((
+			short_id(&commit),
+			commit.message().unwrap_or_default().into(),
+		));
+	}
+	Ok(messages)
+}
+
+fn short_id(commit: &git2::Commit) -> String {
+	commit.id().to_string()[..7].into()
+}
+
+/// Drops comments and everything below the scissors line,
+/// the same way `git commit` does with `--cleanup=strip`
+fn clean(message: &str, strip_comments: bool) -> Vec<&str> {
+	let mut lines: Vec<&str> = message
+		.lines()
+		.take_while(|line| !strip_comments || *line != SCISSORS)
+		.filter(|line| !strip_comments || !line.starts_with('#'))
+		.map(str::trim_end)
+		.collect();
+	while lines.last().is_some_and(|line| line.is_empty()) {
+		lines.pop();
+	}
+	lines
+}
+
+/// Lints a message that still contains git's comments, e.g. from the editor
+pub fn lint(config: &Config, message: &str) -> Vec<Diagnostic> {
+	check(config, clean(message, true))
+}
+
+fn check(config: &Config, lines: Vec<&str>) -> Vec<Diagnostic> {
+	let Some(header) = lines.first() else {
+		return vec![Diagnostic::new(1, ParseError::Empty.to_string())];
+	};
+	// generated by git, nothing to check here
+	if header.starts_with("Merge ") || header.starts_with("fixup! ") || header.starts_with("squash! ")
+	{
+		return Vec::new();
+	}
+
+	let mut diagnostics = Vec::new();
+	let commit = match ConventionalCommit::parse(header) {
+		Ok(commit) => commit,
+		Err(e) => return vec![Diagnostic::new(1, e.to_string())],
+	};
+	let ConventionalCommit {
+		type_,
+		scope,
+		description,
+		..
+	} = &commit;
+
+	// with the emoji mode the type can be an emoji as well
+	if config.resolve_type(type_).is_none() {
+		diagnostics.push(Diagnostic::new(
+			1,
+			format!(
+				"Unknown type `{type_}`, expected one of: {}",
+				config.get_type_items().join(", ")
+			),
+		));
+	}
+	if let Some(scope) = scope {
+		if config.is_scope_strict() && !config.get_scope_items().contains(scope) {
+			diagnostics.push(Diagnostic::new(
+				1,
+				format!(
+					"Unknown scope `{scope}`, expected one of: {}",
+					config.get_scope_items().join(", ")
+				),
+			));
+		}
+	}
+	let type_len = emoji::width(type_)
+		+ commit
+			.emoji
+			.as_deref()
+			.map_or(0, |emoji| emoji::width(emoji) + 1);
+	let max_len = calc_max_len(&type_len, &scope.as_ref().map_or(0, String::len));
+	// the prompts only warn about the length as well
+	if description.len() > max_len {
+		diagnostics.push(Diagnostic::warning(
+			1,
+			format!(
+				"Description is {} chars long, only {max_len} are allowed",
+				description.len()
+			),
+		));
+	}
+	// the body is checked separately so the header diagnostics are still reported
+	if lines.get(1).is_some_and(|line| !line.is_empty()) {
+		diagnostics.push(Diagnostic::new(2, ParseError::MissingBlankLine.to_string()));
+	}
+	diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn lines(diagnostics: Vec<Diagnostic>) -> Vec<usize> {
+		diagnostics
+			.iter()
+			.map(|diagnostic| diagnostic.line)
+			.collect()
+	}
+
+	#[test]
+	fn test_valid() {
+		let config = Config::default();
+		assert_eq!(lint(&config, "feat: add lint"), vec![]);
+		assert_eq!(
+			lint(&config, "fix(conf)!: drop old format\n\nbody\n"),
+			vec![]
+		);
+		assert_eq!(
+			lint(
+				&config,
+				"docs: readme\n# Please enter the commit message\n\n# comment"
+			),
+			vec![]
+		);
+		assert_eq!(lint(&config, "Merge branch 'main' into dev"), vec![]);
+	}
+
+	#[test]
+	fn test_emoji() {
+		let config = Config::default();
+		assert_eq!(lint(&config, "✨ feat: add emoji"), vec![]);
+		assert_eq!(lint(&config, ":sparkles: feat: add emoji"), vec![]);
+		// without the emoji mode the gitmojis aren't types
+		assert_eq!(lines(lint(&config, ":sparkles:: add emoji")), vec![1]);
+
+		let config = Config::from_toml("[emoji]\nmode = 'replace'");
+		assert_eq!(lint(&config, ":sparkles:: add emoji"), vec![]);
+		assert_eq!(lint(&config, "✨(lint): add emoji"), vec![]);
+		assert_eq!(lines(lint(&config, "🦀: add emoji")), vec![1]);
+	}
+
+	#[test]
+	fn test_invalid_header() {
+		let config = Config::default();
+		assert_eq!(lines(lint(&config, "add lint")), vec![1]);
+		assert_eq!(lines(lint(&config, "feat:add lint")), vec![1]);
+		assert_eq!(lines(lint(&config, "# only a comment\n")), vec![1]);
+		assert_eq!(lines(lint(&config, "feat(): add lint")), vec![1]);
+	}
+
+	#[test]
+	fn test_unknown_type() {
+		let config = Config::default();
+		let diagnostics = lint(&config, "feature: add lint");
+		assert_eq!(diagnostics.len(), 1);
+		assert!(diagnostics[0].message.contains("`feature`"));
+	}
+
+	#[test]
+	fn test_length() {
+		let config = Config::default();
+		// 50 chars in total
+		let header = format!("feat(lint): {}", "a".repeat(38));
+		assert_eq!(lint(&config, &header), vec![]);
+		let diagnostics = lint(&config, &format!("{header}a"));
+		assert!(diagnostics[0].warning);
+		assert_eq!(lines(diagnostics), vec![1]);
+		assert!(report(
+			&config,
+			vec![("-".into(), format!("{header}a"))],
+			true
+		));
+	}
+
+	#[test]
+	fn test_stored_comments() {
+		let config = Config::default();
+		let message = "fix: handle unborn HEAD\n\n#42 was reported twice";
+		assert!(report(&config, vec![("-".into(), message.into())], false));
+		assert_eq!(
+			lines(check(&config, clean("feat: add lint\n#42", false))),
+			vec![2]
+		);
+		assert_eq!(check(&config, clean("feat: add lint\n#42", true)), vec![]);
+	}
+
+	#[test]
+	fn test_missing_blank_line() {
+		let config = Config::default();
+		assert_eq!(lines(lint(&config, "feat: add lint\nbody")), vec![2]);
+	}
+
+	#[test]
+	fn test_scissors() {
+		let config = Config::default();
+		let message = format!("feat: add lint\n{SCISSORS}\ndiff --git a/file b/file");
+		assert_eq!(lint(&config, &message), vec![]);
+	}
+}
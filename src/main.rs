@@ -1,7 +1,23 @@
+use std::io::{
+	stdin,
+	IsTerminal,
+};
+
+mod bump;
+mod changelog;
 mod cli;
 mod conf;
 mod git;
+mod hook;
 mod inputs;
+mod lint;
+mod push;
+mod review;
+mod sign;
+mod stage;
+mod suggest;
+#[cfg(test)]
+mod test_utils;
 mod utils;
 
 fn main() {
@@ -12,14 +28,68 @@ fn main() {
 	.expect("Error setting ctrl+c handler");
 
 	let args = cli::setup().get_matches();
-	let config = conf::Config::get().expect("Failed to read from configuration file");
-	let inputs = inputs::get_inputs(&config);
-	if let Ok(inputs) = inputs {
-		git::commit_changes(config.get_signoff(), &args, &inputs).expect("Failed to commit changes");
-	} else {
-		let term = console::Term::stderr();
-		let _ = term.show_cursor();
-		std::process::exit(1);
-		// inputs.expect("Failed to get scope");
+	let config = match conf::Config::get() {
+		Ok(config) => config,
+		Err(e) => {
+			utils::output_failure(&format!("Failed to read the configuration: {e:#}"));
+			std::process::exit(2);
+		}
+	};
+	let subcommand = match args.subcommand() {
+		Some(("lint", args)) => Some(lint::run(&config, args)),
+		Some(("changelog", args)) => Some(changelog::run(&config, args)),
+		Some(("bump", args)) => Some(bump::run(&config, args)),
+		Some(("hook", hook_args)) => Some(hook::run(&config, &args, hook_args)),
+		_ => None,
+	};
+	if let Some(result) = subcommand {
+		match result {
+			Ok(true) => return,
+			Ok(false) => std::process::exit(1),
+			Err(e) => {
+				utils::output_failure(&format!("{e:#}"));
+				std::process::exit(2);
+			}
+		}
+	}
+	for warning in config.get_warnings() {
+		utils::output_info(warning);
+	}
+	if args.get_flag("patch") {
+		if let Err(e) = stage::run(&git::get_repo()) {
+			utils::output_failure(&format!("{e:#}"));
+			std::process::exit(1);
+		}
+	}
+	git::stage_requested(&git::get_repo(), &args);
+	// before doing anything check index
+	git::check_emptiness(&git::get_repo());
+	if !args.get_flag("no-verify") {
+		if let Err(e) = git::verify_staged(&git::get_repo()) {
+			utils::output_failure(&format!("{e:#}"));
+			std::process::exit(1);
+		}
+	}
+	let message = inputs::get_inputs(&config, &args).and_then(|mut inputs| {
+		let repo = git::get_repo();
+		// nobody is there to review the message
+		if args.get_flag("yes") || !stdin().is_terminal() {
+			Ok(Some(git::gen_message(&repo, &config, &inputs)))
+		} else {
+			review::run(&repo, &config, &mut inputs)
+		}
+	});
+	match message {
+		Ok(Some(message)) => git::commit_changes(&args, &message).expect("Failed to commit changes"),
+		Ok(None) => {
+			utils::output_info("Commit aborted");
+			std::process::exit(1);
+		}
+		Err(e) => {
+			let term = console::Term::stderr();
+			let _ = term.show_cursor();
+			utils::output_failure(&format!("{e:#}"));
+			std::process::exit(1);
+		}
 	}
 }
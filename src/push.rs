@@ -0,0 +1,228 @@
+use std::{
+	cell::RefCell,
+	io::{
+		stdin,
+		IsTerminal,
+	},
+};
+
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use git2::{
+	Cred,
+	CredentialType,
+	PushOptions,
+	RemoteCallbacks,
+	Repository,
+};
+
+use crate::{
+	inputs::{
+		ask_for_upstream,
+		select_remote,
+	},
+	utils::{
+		output_failure,
+		output_success,
+	},
+};
+
+/// libgit2 keeps calling the credentials callback as long as authentication fails
+const MAX_AUTH_ATTEMPTS: usize = 3;
+
+struct Upstream {
+	remote: String,
+	merge: String,
+}
+
+/// Push the current branch to its upstream
+/// and offer to set one if there is none
+pub fn push(repo: &Repository) -> Result<()> {
+	let head = repo.head().context("Failed to read HEAD")?;
+	if !head.is_branch() {
+		bail!("HEAD is detached, there is no branch to push");
+	}
+	let refname = head.name().context("Branch name is not valid UTF-8")?;
+	let branch = head.shorthand().context("Branch name is not valid UTF-8")?;
+
+	let (upstream, new_upstream) = match get_upstream(repo, branch) {
+		Some(upstream) => (upstream, false),
+		None => (choose_upstream(repo, branch, refname)?, true),
+	};
+	push_ref(
+		repo,
+		&upstream.remote,
+		&format!("{refname}:{}", upstream.merge),
+	)?;
+	if new_upstream {
+		set_upstream(repo, branch, &upstream)?;
+	}
+	output_success(&format!("Pushed {branch} to {}", upstream.remote));
+	Ok(())
+}
+
+fn get_upstream(repo: &Repository, branch: &str) -> Option<Upstream> {
+	let config = repo.config().ok()?;
+	let remote = config.get_string(&format!("branch.{branch}.remote")).ok()?;
+	let merge = config.get_string(&format!("branch.{branch}.merge")).ok()?;
+	Some(Upstream { remote, merge })
+}
+
+fn choose_upstream(repo: &Repository, branch: &str, refname: &str) -> Result<Upstream> {
+	let remotes = repo.remotes()?;
+	let remotes: Vec<&str> = remotes.iter().flatten().collect();
+	if remotes.is_empty() {
+		bail!("There are no remotes to push to");
+	}
+	if !stdin().is_terminal() {
+		bail!("Branch {branch} has no upstream");
+	}
+	let remote = if remotes.contains(&"origin") {
+		"origin"
+	} else if remotes.len() == 1 {
+		remotes[0]
+	} else {
+		remotes[select_remote(&remotes)]
+	};
+	if !ask_for_upstream(branch, remote) {
+		bail!("Branch {branch} has no upstream");
+	}
+	Ok(Upstream {
+		remote: remote.into(),
+		merge: refname.into(),
+	})
+}
+
+/// Same as `git push --set-upstream`
+fn set_upstream(repo: &Repository, branch: &str, upstream: &Upstream) -> Result<()> {
+	let mut config = repo.config()?;
+	config.set_str(&format!("branch.{branch}.remote"), &upstream.remote)?;
+	config.set_str(&format!("branch.{branch}.merge"), &upstream.merge)?;
+	Ok(())
+}
+
+fn push_ref(repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+	let config = repo.config()?;
+	let mut remote = repo
+		.find_remote(remote)
+		.with_context(|| format!("Failed to find remote {remote}"))?;
+	let rejected = RefCell::new(Vec::new());
+	let mut attempts = 0;
+
+	let mut callbacks = RemoteCallbacks::new();
+	callbacks.credentials(|url, username, allowed| {
+		attempts += 1;
+		if attempts > MAX_AUTH_ATTEMPTS {
+			return Err(git2::Error::from_str("Authentication failed"));
+		}
+		// libgit2 asks for the user first if the ssh url doesn't contain one
+		if allowed.contains(CredentialType::USERNAME) {
+			Cred::username(username.unwrap_or("git"))
+		} else if allowed.contains(CredentialType::SSH_KEY) {
+			Cred::ssh_key_from_agent(username.unwrap_or("git"))
+		} else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+			Cred::credential_helper(&config, url, username)
+		} else {
+			Cred::default()
+		}
+	});
+	callbacks.push_update_reference(|refname, status| {
+		if let Some(status) = status {
+			rejected.borrow_mut().push(format!("{refname}: {status}"));
+		}
+		Ok(())
+	});
+
+	let mut options = PushOptions::new();
+	options.remote_callbacks(callbacks);
+	remote.push(&[refspec], Some(&mut options))?;
+
+	let rejected = rejected.take();
+	if !rejected.is_empty() {
+		for reason in &rejected {
+			output_failure(&format!("Rejected {reason}"));
+		}
+		bail!("The remote rejected {} ref(s)", rejected.len());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+	use crate::test_utils::commit;
+
+	fn setup() -> (TempDir, Repository, Repository) {
+		let dir = TempDir::new().unwrap();
+		let remote = Repository::init_bare(dir.path().join("remote.git")).unwrap();
+		let repo = Repository::init(dir.path().join("local")).unwrap();
+		repo
+			.remote("origin", dir.path().join("remote.git").to_str().unwrap())
+			.unwrap();
+		commit(&repo, "chore: init");
+		(dir, repo, remote)
+	}
+
+	fn branch(repo: &Repository) -> String {
+		repo.head().unwrap().shorthand().unwrap().into()
+	}
+
+	#[test]
+	fn test_push_upstream() {
+		let (_dir, repo, remote) = setup();
+		let branch = branch(&repo);
+		let upstream = Upstream {
+			remote: "origin".into(),
+			merge: format!("refs/heads/{branch}"),
+		};
+		set_upstream(&repo, &branch, &upstream).unwrap();
+		let oid = commit(&repo, "feat: push me");
+
+		push(&repo).unwrap();
+		let pushed = remote
+			.find_reference(&format!("refs/heads/{branch}"))
+			.unwrap();
+		assert_eq!(pushed.target(), Some(oid));
+	}
+
+	#[test]
+	fn test_upstream_config() {
+		let (_dir, repo, _remote) = setup();
+		let branch = branch(&repo);
+		assert!(get_upstream(&repo, &branch).is_none());
+		let upstream = Upstream {
+			remote: "origin".into(),
+			merge: "refs/heads/other".into(),
+		};
+		set_upstream(&repo, &branch, &upstream).unwrap();
+		let upstream = get_upstream(&repo, &branch).unwrap();
+		assert_eq!(upstream.remote, "origin");
+		assert_eq!(upstream.merge, "refs/heads/other");
+	}
+
+	#[test]
+	fn test_push_rejected() {
+		let (dir, repo, _remote) = setup();
+		let branch = branch(&repo);
+		let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+		push_ref(&repo, "origin", &refspec).unwrap();
+
+		// a second clone moves the remote branch ahead
+		let other = Repository::clone(
+			dir.path().join("remote.git").to_str().unwrap(),
+			dir.path().join("other"),
+		)
+		.unwrap();
+		commit(&other, "feat: other");
+		push_ref(&other, "origin", &refspec).unwrap();
+
+		commit(&repo, "feat: diverged");
+		assert!(push_ref(&repo, "origin", &refspec).is_err());
+	}
+}
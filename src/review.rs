@@ -0,0 +1,88 @@
+use anyhow::Result;
+use colored::Colorize;
+use git2::Repository;
+use strum::Display;
+
+use crate::{
+	conf::Config,
+	git::{
+		gen_message,
+		get_diffstat,
+	},
+	inputs::{
+		ask_again,
+		ask_for_review,
+		select_field,
+		Inputs,
+	},
+	lint::lint,
+	utils::output_info,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+pub enum ReviewChoice {
+	Commit,
+	#[strum(serialize = "Edit the message")]
+	Edit,
+	#[strum(serialize = "Go back to a prompt")]
+	Back,
+	Abort,
+}
+
+impl ReviewChoice {
+	pub const ALL: [Self; 4] = [Self::Commit, Self::Edit, Self::Back, Self::Abort];
+}
+
+/// Shows the message and the staged changes until the user commits or aborts
+///
+/// Returns the message to commit, `None` if the commit was aborted
+pub fn run<'a>(
+	repo: &Repository,
+	config: &'a Config,
+	inputs: &mut Inputs<'a>,
+) -> Result<Option<String>> {
+	loop {
+		let message = gen_message(repo, config, inputs);
+		print_summary(&message, &get_diffstat(repo)?);
+		match ask_for_review() {
+			ReviewChoice::Commit => return Ok(Some(message)),
+			ReviewChoice::Edit => return edit_message(config, &message),
+			ReviewChoice::Back => ask_again(config, inputs, select_field())?,
+			ReviewChoice::Abort => return Ok(None),
+		}
+	}
+}
+
+fn print_summary(message: &str, diffstat: &str) {
+	println!();
+	for line in message.lines() {
+		println!("  {}", line.bold());
+	}
+	println!();
+	for line in diffstat.lines() {
+		println!("  {line}");
+	}
+	println!();
+}
+
+/// Like `git commit`, an empty message aborts the commit
+fn edit_message(config: &Config, message: &str) -> Result<Option<String>> {
+	let template = format!(
+		"{message}\n\n# Lines starting with '#' will be ignored, an empty message aborts the commit.\n"
+	);
+	let edited = edit::edit(template)?;
+	let edited = edited
+		.lines()
+		.filter(|line| !line.starts_with('#'))
+		.collect::<Vec<_>>()
+		.join("\n");
+	let edited = edited.trim();
+	if edited.is_empty() {
+		return Ok(None);
+	}
+	// the prompts can't produce an invalid message anymore, the editor can
+	for diagnostic in lint(config, edited) {
+		output_info(&diagnostic.to_string());
+	}
+	Ok(Some(edited.into()))
+}
@@ -0,0 +1,282 @@
+use std::{
+	io::Write,
+	path::PathBuf,
+	process::{
+		Command,
+		Stdio,
+	},
+};
+
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use directories::BaseDirs;
+use git2::{
+	Config,
+	Signature,
+};
+use tempfile::NamedTempFile;
+
+/// `gpg.format` as understood by git
+#[derive(Debug, PartialEq)]
+pub enum Format {
+	OpenPgp,
+	Ssh,
+}
+
+impl Format {
+	fn from_config(config: &Config) -> Result<Self> {
+		match get_string(config, "gpg.format").as_deref() {
+			None | Some("openpgp") => Ok(Self::OpenPgp),
+			Some("ssh") => Ok(Self::Ssh),
+			// x.509 is another bag of worms...
+			Some(format) => bail!("unsupported gpg.format: {format}"),
+		}
+	}
+
+	fn default_program(&self) -> &'static str {
+		match self {
+			Self::OpenPgp => "gpg",
+			Self::Ssh => "ssh-keygen",
+		}
+	}
+
+	fn program_key(&self) -> &'static str {
+		match self {
+			Self::OpenPgp => "gpg.openpgp.program",
+			Self::Ssh => "gpg.ssh.program",
+		}
+	}
+}
+
+/// Everything needed to sign a commit buffer the same way `git commit -S` would
+#[derive(Debug, PartialEq)]
+pub struct Signer {
+	format: Format,
+	program: String,
+	key: Option<String>,
+}
+
+impl Signer {
+	/// Returns `None` if `commit.gpgsign` is unset or false
+	pub fn from_config(config: &Config, committer: &Signature) -> Result<Option<Self>> {
+		if !config.get_bool("commit.gpgsign").unwrap_or_default() {
+			return Ok(None);
+		}
+		let format = Format::from_config(config)?;
+		let program = get_string(config, format.program_key())
+			// `gpg.program` is the legacy name for the openpgp program
+			.or_else(|| match format {
+				Format::OpenPgp => get_string(config, "gpg.program"),
+				Format::Ssh => None,
+			})
+			.unwrap_or_else(|| format.default_program().into());
+		let key = get_string(config, "user.signingkey").or_else(|| match format {
+			// git picks the key of the committer, not gpg's default key
+			Format::OpenPgp => Some(format!(
+				"{} <{}>",
+				committer.name().unwrap_or_default(),
+				committer.email().unwrap_or_default()
+			)),
+			Format::Ssh => None,
+		});
+		Ok(Some(Self {
+			format,
+			program,
+			key,
+		}))
+	}
+
+	/// Creates a detached, armored signature over `content`
+	pub fn sign(&self, content: &str) -> Result<String> {
+		match self.format {
+			Format::OpenPgp => self.sign_openpgp(content),
+			Format::Ssh => self.sign_ssh(content),
+		}
+	}
+
+	fn sign_openpgp(&self, content: &str) -> Result<String> {
+		let mut command = Command::new(&self.program);
+		command.args(["--status-fd=2", "-bsa"]);
+		if let Some(key) = &self.key {
+			command.args(["-u", key]);
+		}
+		run(command, content)
+	}
+
+	fn sign_ssh(&self, content: &str) -> Result<String> {
+		let Some(key) = &self.key else {
+			bail!("user.signingkey has to be set to sign with ssh");
+		};
+		let mut command = Command::new(&self.program);
+		command.args(["-Y", "sign", "-n", "git"]);
+		// literal public keys have to be written to a file for ssh-keygen
+		// the private key is then expected to live in the ssh-agent
+		let _literal_key = if let Some(literal) = literal_ssh_key(key) {
+			let mut file = NamedTempFile::new()?;
+			writeln!(file, "{literal}")?;
+			command.arg("-U").arg("-f").arg(file.path());
+			Some(file)
+		} else {
+			command.arg("-f").arg(expand_home(key));
+			None
+		};
+		run(command, content)
+	}
+}
+
+fn get_string(config: &Config, key: &str) -> Option<String> {
+	config.get_string(key).ok().filter(|val| !val.is_empty())
+}
+
+/// Key types of `ssh-keygen -t`, a path can't start with them
+static SSH_KEY_PREFIXES: [&str; 4] = ["ssh-", "ecdsa-sha2-", "sk-ssh-", "sk-ecdsa-sha2-"];
+
+fn literal_ssh_key(key: &str) -> Option<&str> {
+	if let Some(key) = key.strip_prefix("key::") {
+		Some(key)
+	} else if SSH_KEY_PREFIXES
+		.iter()
+		.any(|prefix| key.starts_with(prefix))
+	{
+		Some(key)
+	} else {
+		None
+	}
+}
+
+fn expand_home(path: &str) -> PathBuf {
+	match (path.strip_prefix("~/"), BaseDirs::new()) {
+		(Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+		_ => PathBuf::from(path),
+	}
+}
+
+fn run(mut command: Command, content: &str) -> Result<String> {
+	let program = command.get_program().to_string_lossy().into_owned();
+	let mut child = command
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.with_context(|| format!("Failed to run {program}"))?;
+	// stdin has to be dropped so the signing program knows we're done
+	child
+		.stdin
+		.take()
+		.context("Failed to open stdin of signing program")?
+		.write_all(content.as_bytes())?;
+	let output = child.wait_with_output()?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		bail!("{program} failed to sign the commit: {}", stderr.trim());
+	}
+	let signature = String::from_utf8(output.stdout)?;
+	if signature.trim().is_empty() {
+		bail!("{program} did not produce a signature");
+	}
+	Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+
+	fn setup_config(entries: &[(&str, &str)]) -> (TempDir, Config) {
+		let dir = TempDir::new().unwrap();
+		let mut config = Config::open(&dir.path().join("config")).unwrap();
+		for (key, value) in entries {
+			config.set_str(key, value).unwrap();
+		}
+		(dir, config)
+	}
+
+	fn committer() -> Signature<'static> {
+		Signature::now("resin", "resin@example.com").unwrap()
+	}
+
+	#[test]
+	fn test_unsigned() {
+		let (_dir, config) = setup_config(&[]);
+		assert_eq!(Signer::from_config(&config, &committer()).unwrap(), None);
+		let (_dir, config) = setup_config(&[("commit.gpgsign", "false")]);
+		assert_eq!(Signer::from_config(&config, &committer()).unwrap(), None);
+	}
+
+	#[test]
+	fn test_openpgp_defaults() {
+		let (_dir, config) = setup_config(&[("commit.gpgsign", "true")]);
+		assert_eq!(
+			Signer::from_config(&config, &committer()).unwrap(),
+			Some(Signer {
+				format: Format::OpenPgp,
+				program: "gpg".into(),
+				key: Some("resin <resin@example.com>".into()),
+			})
+		);
+	}
+
+	#[test]
+	fn test_program_override() {
+		let (_dir, config) = setup_config(&[
+			("commit.gpgsign", "true"),
+			("gpg.program", "gpg2"),
+			("user.signingkey", "ABCDEF"),
+		]);
+		let signer = Signer::from_config(&config, &committer()).unwrap().unwrap();
+		assert_eq!(signer.program, "gpg2");
+		assert_eq!(signer.key.as_deref(), Some("ABCDEF"));
+
+		let (_dir, config) = setup_config(&[
+			("commit.gpgsign", "true"),
+			("gpg.format", "ssh"),
+			("gpg.program", "gpg2"),
+		]);
+		let signer = Signer::from_config(&config, &committer()).unwrap().unwrap();
+		assert_eq!(signer.format, Format::Ssh);
+		assert_eq!(signer.program, "ssh-keygen");
+	}
+
+	#[test]
+	fn test_unsupported_format() {
+		let (_dir, config) = setup_config(&[("commit.gpgsign", "true"), ("gpg.format", "x509")]);
+		assert!(Signer::from_config(&config, &committer()).is_err());
+	}
+
+	#[test]
+	fn test_literal_ssh_key() {
+		assert_eq!(
+			literal_ssh_key("key::ssh-ed25519 AAAA"),
+			Some("ssh-ed25519 AAAA")
+		);
+		assert_eq!(
+			literal_ssh_key("ssh-ed25519 AAAA"),
+			Some("ssh-ed25519 AAAA")
+		);
+		assert_eq!(
+			literal_ssh_key("ecdsa-sha2-nistp256 AAAA"),
+			Some("ecdsa-sha2-nistp256 AAAA")
+		);
+		assert_eq!(
+			literal_ssh_key("sk-ssh-ed25519@openssh.com AAAA"),
+			Some("sk-ssh-ed25519@openssh.com AAAA")
+		);
+		assert_eq!(
+			literal_ssh_key("sk-ecdsa-sha2-nistp256@openssh.com AAAA"),
+			Some("sk-ecdsa-sha2-nistp256@openssh.com AAAA")
+		);
+		assert_eq!(literal_ssh_key("~/.ssh/id_ed25519.pub"), None);
+	}
+
+	#[test]
+	fn test_ssh_without_key() {
+		let (_dir, config) = setup_config(&[("commit.gpgsign", "true"), ("gpg.format", "ssh")]);
+		let signer = Signer::from_config(&config, &committer()).unwrap().unwrap();
+		assert!(signer.sign("content").is_err());
+	}
+}
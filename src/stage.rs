@@ -0,0 +1,329 @@
+use std::{
+	cell::RefCell,
+	collections::HashSet,
+	fmt::{
+		self,
+		Display,
+	},
+	io::{
+		stdin,
+		IsTerminal,
+	},
+	path::PathBuf,
+};
+
+use anyhow::{
+	bail,
+	Context,
+	Result,
+};
+use colored::Colorize;
+use git2::{
+	ApplyLocation,
+	ApplyOptions,
+	Delta,
+	Diff,
+	DiffOptions,
+	Patch,
+	Repository,
+};
+
+use crate::{
+	inputs::ask_for_hunk,
+	utils::{
+		output_info,
+		output_success,
+	},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HunkChoice {
+	Stage,
+	Skip,
+	/// Stage this and every following hunk of the file
+	StageFile,
+	/// Skip this and every following hunk of the file
+	SkipFile,
+	/// Skip every hunk that is left
+	Done,
+}
+
+impl HunkChoice {
+	pub const ALL: [Self; 5] = [
+		Self::Stage,
+		Self::Skip,
+		Self::StageFile,
+		Self::SkipFile,
+		Self::Done,
+	];
+}
+
+impl Display for HunkChoice {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Stage => "Stage",
+			Self::Skip => "Skip",
+			Self::StageFile => "Stage the rest of the file",
+			Self::SkipFile => "Skip the rest of the file",
+			Self::Done => "Done",
+		})
+	}
+}
+
+/// Identifies a hunk across the diff we show and the diff we apply
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct HunkId {
+	path: PathBuf,
+	old_start: u32,
+	new_start: u32,
+}
+
+#[derive(Debug)]
+struct Hunk {
+	id: HunkId,
+	header: String,
+	lines: Vec<(char, String)>,
+}
+
+impl Hunk {
+	fn print(&self) {
+		println!("{}", self.id.path.display().to_string().bold());
+		println!("{}", self.header.trim_end().cyan());
+		for (origin, content) in &self.lines {
+			let line = format!("{origin}{}", content.trim_end_matches('\n'));
+			match origin {
+				'+' => println!("{}", line.green()),
+				'-' => println!("{}", line.red()),
+				_ => println!("{line}"),
+			}
+		}
+	}
+}
+
+/// Entry point of `resin --patch`, a `git add -p` that runs before the prompts
+pub fn run(repo: &Repository) -> Result<()> {
+	if !stdin().is_terminal() {
+		bail!("--patch needs a terminal to ask which hunks to stage");
+	}
+	let diff = get_unstaged(repo)?;
+	let hunks = get_hunks(&diff)?;
+	if hunks.is_empty() {
+		output_info("There are no unstaged changes");
+		return Ok(());
+	}
+	let selected = select(&hunks);
+	if selected.is_empty() {
+		return Ok(());
+	}
+	stage(repo, &diff, &selected)?;
+	output_success(&format!(
+		"Staged {} of {} hunks",
+		selected.len(),
+		hunks.len()
+	));
+	Ok(())
+}
+
+/// Changes in the working tree that are not in the index, new files included
+fn get_unstaged(repo: &Repository) -> Result<Diff<'_>> {
+	let mut options = DiffOptions::new();
+	options
+		.include_untracked(true)
+		.recurse_untracked_dirs(true)
+		.show_untracked_content(true);
+	repo
+		.diff_index_to_workdir(None, Some(&mut options))
+		.context("Failed to diff the working tree")
+}
+
+fn get_hunks(diff: &Diff) -> Result<Vec<Hunk>> {
+	let mut hunks = Vec::new();
+	for i in 0..diff.deltas().len() {
+		let Some(patch) = Patch::from_diff(diff, i)? else {
+			// binary files can't be split
+			continue;
+		};
+		let Some(path) = patch.delta().new_file().path().map(PathBuf::from) else {
+			continue;
+		};
+		for hunk_idx in 0..patch.num_hunks() {
+			let (hunk, line_count) = patch.hunk(hunk_idx)?;
+			let mut lines = Vec::with_capacity(line_count);
+			for line_idx in 0..line_count {
+				let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+				lines.push((
+					line.origin(),
+					String::from_utf8_lossy(line.content()).into_owned(),
+				));
+			}
+			hunks.push(Hunk {
+				id: HunkId {
+					path: path.clone(),
+					old_start: hunk.old_start(),
+					new_start: hunk.new_start(),
+				},
+				header: String::from_utf8_lossy(hunk.header()).into_owned(),
+				lines,
+			});
+		}
+	}
+	Ok(hunks)
+}
+
+fn select(hunks: &[Hunk]) -> HashSet<HunkId> {
+	let mut selected = HashSet::new();
+	// the decision for the rest of the current file
+	let mut rest_of_file: Option<(&PathBuf, bool)> = None;
+	for (i, hunk) in hunks.iter().enumerate() {
+		if let Some((path, stage)) = rest_of_file {
+			if *path == hunk.id.path {
+				if stage {
+					selected.insert(hunk.id.clone());
+				}
+				continue;
+			}
+			rest_of_file = None;
+		}
+		hunk.print();
+		match ask_for_hunk(&format!("({}/{})", i + 1, hunks.len())) {
+			HunkChoice::Stage => {
+				selected.insert(hunk.id.clone());
+			}
+			HunkChoice::Skip => {}
+			HunkChoice::StageFile => {
+				selected.insert(hunk.id.clone());
+				rest_of_file = Some((&hunk.id.path, true));
+			}
+			HunkChoice::SkipFile => rest_of_file = Some((&hunk.id.path, false)),
+			HunkChoice::Done => break,
+		}
+	}
+	selected
+}
+
+/// Applies only the `selected` hunks of `diff` to the index
+fn stage(repo: &Repository, diff: &Diff, selected: &HashSet<HunkId>) -> Result<()> {
+	let current = RefCell::new(PathBuf::new());
+	let mut options = ApplyOptions::new();
+	options.delta_callback(|delta| {
+		let Some(delta) = delta else {
+			return false;
+		};
+		// the index doesn't know untracked files yet, they are added below
+		if delta.status() == Delta::Untracked {
+			return false;
+		}
+		let Some(path) = delta.new_file().path().map(PathBuf::from) else {
+			return false;
+		};
+		// skipping every hunk of a new file would still add it empty
+		let keep = selected.iter().any(|id| id.path == path);
+		*current.borrow_mut() = path;
+		keep
+	});
+	options.hunk_callback(|hunk| {
+		hunk.is_some_and(|hunk| {
+			selected.contains(&HunkId {
+				path: current.borrow().clone(),
+				old_start: hunk.old_start(),
+				new_start: hunk.new_start(),
+			})
+		})
+	});
+	repo
+		.apply(diff, ApplyLocation::Index, Some(&mut options))
+		.context("Failed to stage the hunks")?;
+
+	let mut index = repo.index()?;
+	index.read(true)?;
+	for delta in diff.deltas() {
+		let Some(path) = delta.new_file().path() else {
+			continue;
+		};
+		// a new file is a single hunk
+		if delta.status() == Delta::Untracked && selected.iter().any(|id| id.path == path) {
+			index.add_path(path)?;
+		}
+	}
+	index.write()?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fs,
+		path::Path,
+	};
+
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+	use crate::test_utils::setup_repo;
+
+	fn setup() -> (TempDir, Repository) {
+		let content: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+		setup_repo(&[("file.txt", &content)])
+	}
+
+	fn staged(repo: &Repository, path: &str) -> String {
+		let index = repo.index().unwrap();
+		let entry = index.get_path(Path::new(path), 0).unwrap();
+		let blob = repo.find_blob(entry.id).unwrap();
+		String::from_utf8(blob.content().to_vec()).unwrap()
+	}
+
+	#[test]
+	fn test_stage_single_hunk() {
+		let (dir, repo) = setup();
+		let path = dir.path().join("file.txt");
+		let content = fs::read_to_string(&path)
+			.unwrap()
+			.replace("line 2\n", "line two\n")
+			.replace("line 19\n", "line nineteen\n");
+		fs::write(&path, &content).unwrap();
+
+		let diff = get_unstaged(&repo).unwrap();
+		let hunks = get_hunks(&diff).unwrap();
+		assert_eq!(hunks.len(), 2);
+		assert!(hunks[0].lines.contains(&('+', String::from("line two\n"))));
+
+		let selected = HashSet::from([hunks[1].id.clone()]);
+		stage(&repo, &diff, &selected).unwrap();
+		let index = staged(&repo, "file.txt");
+		assert!(index.contains("line 2\n"));
+		assert!(index.contains("line nineteen\n"));
+		// the working tree is untouched
+		assert_eq!(fs::read_to_string(&path).unwrap(), content);
+	}
+
+	#[test]
+	fn test_stage_new_file() {
+		let (dir, repo) = setup();
+		fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+		fs::write(dir.path().join("skipped.txt"), "skipped\n").unwrap();
+
+		let diff = get_unstaged(&repo).unwrap();
+		let hunks = get_hunks(&diff).unwrap();
+		let new = hunks
+			.iter()
+			.find(|hunk| hunk.id.path == Path::new("new.txt"))
+			.unwrap();
+		stage(&repo, &diff, &HashSet::from([new.id.clone()])).unwrap();
+		assert_eq!(staged(&repo, "new.txt"), "new\n");
+		assert!(repo
+			.index()
+			.unwrap()
+			.get_path(Path::new("skipped.txt"), 0)
+			.is_none());
+	}
+
+	#[test]
+	fn test_patch_conflicts() {
+		let parse = |args: &[&str]| crate::cli::setup().try_get_matches_from(args);
+		assert!(parse(&["resin", "--patch"]).is_ok());
+		assert!(parse(&["resin", "--patch", "--all"]).is_err());
+		assert!(parse(&["resin", "-P", "-u"]).is_err());
+	}
+}
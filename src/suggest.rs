@@ -0,0 +1,291 @@
+use std::{
+	cmp::Reverse,
+	collections::HashMap,
+	fs,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+use git2::Repository;
+use regex::Regex;
+
+use crate::{
+	conf::Config,
+	git::get_staged_paths,
+};
+
+/// Directories that say nothing about what changed
+static GENERIC_DIRS: [&str; 8] = [
+	"src", "lib", "crates", "packages", "tests", "test", "include", "app",
+];
+
+/// Scope for the staged changes, preselected in the prompt
+pub fn suggest_scope(config: &Config, repo: &Repository) -> Option<String> {
+	let paths = get_staged_paths(repo);
+	let suggestion = from_globs(config.get_scope_paths(), &paths)
+		.or_else(|| {
+			let members = repo.workdir().map(workspace_members).unwrap_or_default();
+			from_workspace(&members, &paths)
+		})
+		.or_else(|| from_common_dir(&paths))?;
+	// a suggestion that would be rejected is no help
+	if config.is_scope_strict() && !config.get_scope_items().contains(&suggestion) {
+		return None;
+	}
+	Some(suggestion)
+}
+
+/// Type for the staged changes, e.g. `docs` if only markdown files changed
+///
+/// Returns the configured item, so a capitalized `Docs` is found as well
+pub fn suggest_type<'a>(config: &'a Config, repo: &Repository) -> Option<&'a str> {
+	let paths = get_staged_paths(repo);
+	let suggestion = from_globs(config.get_type_paths(), &paths)?;
+	config
+		.get_type_items()
+		.iter()
+		.find(|item| item.eq_ignore_ascii_case(&suggestion))
+		.map(String::as_str)
+}
+
+/// Gitignore-like globs: `*.md` matches at any depth, `src/conf/` everything below it
+fn glob_to_regex(glob: &str) -> Option<Regex> {
+	let glob = glob.trim_start_matches('/');
+	let anchored = glob.trim_end_matches('/').contains('/');
+	let mut regex = String::from(if anchored { "^" } else { "(?:^|/)" });
+	let mut chars = glob.trim_end_matches('/').chars().peekable();
+	while let Some(char) = chars.next() {
+		match char {
+			'*' if chars.peek() == Some(&'*') => {
+				chars.next();
+				if chars.peek() == Some(&'/') {
+					chars.next();
+					regex.push_str("(?:.*/)?");
+				} else {
+					regex.push_str(".*");
+				}
+			}
+			'*' => regex.push_str("[^/]*"),
+			'?' => regex.push_str("[^/]"),
+			char => regex.push_str(&regex::escape(&char.to_string())),
+		}
+	}
+	// a directory matches everything inside of it
+	regex.push_str("(?:$|/)");
+	Regex::new(&regex).ok()
+}
+
+/// The item every path maps to, the most specific glob wins
+fn from_globs(globs: &HashMap<String, String>, paths: &[PathBuf]) -> Option<String> {
+	if globs.is_empty() || paths.is_empty() {
+		return None;
+	}
+	let mut globs: Vec<(Regex, &String, &String)> = globs
+		.iter()
+		.filter_map(|(glob, item)| Some((glob_to_regex(glob)?, item, glob)))
+		.collect();
+	// longer globs are more specific, the rest only keeps it deterministic
+	globs.sort_by_key(|(.., glob)| (Reverse(glob.len()), *glob));
+	let mut items = paths.iter().map(|path| {
+		let path = path.to_string_lossy();
+		globs
+			.iter()
+			.find(|(regex, ..)| regex.is_match(&path))
+			.map(|(_, item, _)| *item)
+	});
+	let first = items.next()??;
+	// mapping a glob to "" turns off a default
+	if first.is_empty() {
+		return None;
+	}
+	items.all(|item| item == Some(first)).then(|| first.clone())
+}
+
+/// `[workspace] members` of the Cargo.toml in `root`
+fn workspace_members(root: &Path) -> Vec<PathBuf> {
+	let Ok(content) = fs::read_to_string(root.join("Cargo.toml")) else {
+		return Vec::new();
+	};
+	let Ok(manifest) = content.parse::<toml::Table>() else {
+		return Vec::new();
+	};
+	let Some(members) = manifest
+		.get("workspace")
+		.and_then(|workspace| workspace.get("members"))
+		.and_then(|members| members.as_array())
+	else {
+		return Vec::new();
+	};
+	members
+		.iter()
+		.filter_map(|member| member.as_str())
+		.flat_map(|member| {
+			// `crates/*` is the usual glob
+			match member.strip_suffix("/*") {
+				Some(parent) => fs::read_dir(root.join(parent))
+					.into_iter()
+					.flatten()
+					.flatten()
+					.filter(|entry| entry.path().is_dir())
+					.map(|entry| Path::new(parent).join(entry.file_name()))
+					.collect(),
+				None => vec![PathBuf::from(member)],
+			}
+		})
+		.collect()
+}
+
+/// Name of the workspace member that contains every path
+fn from_workspace(members: &[PathBuf], paths: &[PathBuf]) -> Option<String> {
+	let first = paths.first()?;
+	let member = members.iter().find(|member| first.starts_with(member))?;
+	if !paths.iter().all(|path| path.starts_with(member)) {
+		return None;
+	}
+	Some(member.file_name()?.to_string_lossy().into())
+}
+
+/// The innermost directory that all paths share and that isn't generic like `src`
+fn from_common_dir(paths: &[PathBuf]) -> Option<String> {
+	let mut common: PathBuf = paths.first()?.parent()?.into();
+	for path in &paths[1..] {
+		while !path.starts_with(&common) {
+			if !common.pop() {
+				return None;
+			}
+		}
+	}
+	common
+		.components()
+		.rev()
+		.map(|component| component.as_os_str().to_string_lossy())
+		.find(|name| !GENERIC_DIRS.contains(&name.as_ref()))
+		.map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+	use tempfile::TempDir;
+
+	use super::*;
+
+	fn paths(paths: &[&str]) -> Vec<PathBuf> {
+		paths.iter().map(PathBuf::from).collect()
+	}
+
+	#[test]
+	fn test_glob() {
+		let glob = |glob: &str, path: &str| glob_to_regex(glob).unwrap().is_match(path);
+		assert!(glob("*.md", "README.md"));
+		assert!(glob("*.md", "docs/guide.md"));
+		assert!(!glob("*.md", "README.mdx"));
+		assert!(glob("src/conf/", "src/conf/toml.rs"));
+		assert!(glob("src/conf", "src/conf/toml.rs"));
+		assert!(!glob("src/conf", "src/config.rs"));
+		assert!(glob("src/*.rs", "src/git.rs"));
+		assert!(!glob("src/*.rs", "src/conf/toml.rs"));
+		assert!(glob("src/**/*.rs", "src/conf/toml.rs"));
+		assert!(glob("**/tests/", "crates/a/tests/it.rs"));
+	}
+
+	#[test]
+	fn test_from_globs() {
+		let globs = HashMap::from([
+			("src/".into(), "core".into()),
+			("src/conf/".into(), "conf".into()),
+			("*.md".into(), "docs".into()),
+		]);
+		assert_eq!(
+			from_globs(&globs, &paths(&["src/conf/toml.rs", "src/conf/conf.rs"])).as_deref(),
+			Some("conf")
+		);
+		assert_eq!(
+			from_globs(&globs, &paths(&["src/git.rs"])).as_deref(),
+			Some("core")
+		);
+		assert_eq!(
+			from_globs(&globs, &paths(&["src/git.rs", "README.md"])),
+			None
+		);
+		assert_eq!(from_globs(&globs, &paths(&["Cargo.toml"])), None);
+	}
+
+	#[test]
+	fn test_type_globs() {
+		let globs = crate::conf::Config::default().get_type_paths().clone();
+		let suggest = |changed: &[&str]| from_globs(&globs, &paths(changed));
+		assert_eq!(
+			suggest(&["README.md", "docs/guide/setup.txt"]).as_deref(),
+			Some("docs")
+		);
+		assert_eq!(
+			suggest(&["tests/cli.rs", "crates/parser/tests/parse.rs"]).as_deref(),
+			Some("test")
+		);
+		assert_eq!(
+			suggest(&[".github/workflows/ci.yml"]).as_deref(),
+			Some("ci")
+		);
+		assert_eq!(
+			suggest(&["Cargo.toml", "Cargo.lock", "crates/cli/Cargo.toml"]).as_deref(),
+			Some("build")
+		);
+		assert_eq!(suggest(&["README.md", "src/main.rs"]), None);
+		assert_eq!(suggest(&["tests/cli.rs", "Cargo.toml"]), None);
+
+		let mut globs = globs;
+		globs.insert("*.md".into(), String::new());
+		assert_eq!(from_globs(&globs, &paths(&["README.md"])), None);
+	}
+
+	#[test]
+	fn test_from_common_dir() {
+		assert_eq!(
+			from_common_dir(&paths(&["src/conf/toml.rs", "src/conf/conf.rs"])).as_deref(),
+			Some("conf")
+		);
+		assert_eq!(
+			from_common_dir(&paths(&["src/conf/toml.rs", "src/git.rs"])),
+			None
+		);
+		assert_eq!(
+			from_common_dir(&paths(&["crates/cli/src/main.rs"])).as_deref(),
+			Some("cli")
+		);
+		assert_eq!(from_common_dir(&paths(&["Cargo.toml"])), None);
+	}
+
+	#[test]
+	fn test_from_workspace() {
+		let dir = TempDir::new().unwrap();
+		fs::create_dir_all(dir.path().join("crates/parser")).unwrap();
+		fs::create_dir_all(dir.path().join("crates/cli")).unwrap();
+		fs::write(
+			dir.path().join("Cargo.toml"),
+			"[workspace]\nmembers = ['crates/*', 'xtask']\n",
+		)
+		.unwrap();
+		let mut members = workspace_members(dir.path());
+		members.sort();
+		assert_eq!(members, paths(&["crates/cli", "crates/parser", "xtask"]));
+
+		assert_eq!(
+			from_workspace(
+				&members,
+				&paths(&["crates/parser/src/lib.rs", "crates/parser/Cargo.toml"])
+			)
+			.as_deref(),
+			Some("parser")
+		);
+		assert_eq!(
+			from_workspace(
+				&members,
+				&paths(&["crates/parser/src/lib.rs", "crates/cli/src/main.rs"])
+			),
+			None
+		);
+	}
+}
@@ -0,0 +1,57 @@
+//! Fixtures shared by the tests of several modules
+
+use std::{
+	fs,
+	path::Path,
+};
+
+use git2::{
+	Oid,
+	Repository,
+	Signature,
+	Time,
+};
+use tempfile::TempDir;
+
+/// Repository with an identity, unsigned commits and `files` committed as `chore: init`
+pub fn setup_repo(files: &[(&str, &str)]) -> (TempDir, Repository) {
+	let dir = TempDir::new().unwrap();
+	let repo = Repository::init(dir.path()).unwrap();
+	{
+		let mut config = repo.config().unwrap();
+		config.set_str("user.name", "resin").unwrap();
+		config.set_str("user.email", "resin@example.com").unwrap();
+		config.set_bool("commit.gpgsign", false).unwrap();
+		let mut index = repo.index().unwrap();
+		for (path, content) in files {
+			fs::write(dir.path().join(path), content).unwrap();
+			index.add_path(Path::new(path)).unwrap();
+		}
+		index.write().unwrap();
+	}
+	commit(&repo, "chore: init");
+	(dir, repo)
+}
+
+/// Commits the index on top of HEAD
+pub fn commit(repo: &Repository, message: &str) -> Oid {
+	let sig = Signature::now("resin", "resin@example.com").unwrap();
+	commit_as(repo, message, &sig)
+}
+
+/// Like [`commit`], but at `time` seconds after the epoch
+pub fn commit_at(repo: &Repository, message: &str, time: i64) -> Oid {
+	let sig = Signature::new("resin", "resin@example.com", &Time::new(time, 0)).unwrap();
+	commit_as(repo, message, &sig)
+}
+
+fn commit_as(repo: &Repository, message: &str, sig: &Signature) -> Oid {
+	let tree = repo
+		.find_tree(repo.index().unwrap().write_tree().unwrap())
+		.unwrap();
+	let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+	let parents: Vec<_> = parent.iter().collect();
+	repo
+		.commit(Some("HEAD"), sig, sig, message, &tree, &parents)
+		.unwrap()
+}
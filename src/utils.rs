@@ -34,23 +34,3 @@ where
 	print_fail(error);
 	exit(1);
 }
-
-pub fn parse_jira(name: &str) -> Option<String> {
-	let ticket_regex = regex::Regex::new("([A-Za-z_]{3,}-[0-9]+)").unwrap();
-	ticket_regex
-		.find(name)
-		.map(|hit| String::from(hit.as_str()))
-}
-
-#[cfg(test)]
-mod tests {
-	use pretty_assertions::assert_eq;
-
-	use super::*;
-
-	#[test]
-	fn test_parse_jira() {
-		assert_eq!(parse_jira("Test-123"), Some("Test-123".into()));
-		assert_eq!(parse_jira("main"), None);
-	}
-}